@@ -21,6 +21,7 @@ impl BitBoard {
     pub const NOT_RANK_2: BitBoard = BitBoard(0xffffffffffff00ff);
     pub const NOT_RANK_7: BitBoard = BitBoard(0xff00ffffffffffff);
     pub const NOT_RANK_8: BitBoard = BitBoard(0xffffffffffffff);
+    pub const LIGHT_SQUARES: BitBoard = BitBoard(0x55AA55AA55AA55AA);
 
     pub fn set_bit(self, x: BitBoard) -> BitBoard {
         self | x
@@ -1,3 +1,4 @@
+mod draw;
 mod fen;
 mod game_state;
 mod history;
@@ -14,6 +15,8 @@ use zobrist::ZobristRandoms;
 
 use crate::bitboard::*;
 use crate::defs::*;
+use crate::evaluate::nnue::{Network, Nnue};
+use crate::evaluate::psqt::{self, PHASE_VALUES, PSQT_EG, PSQT_MG};
 
 #[derive(Clone)]
 pub struct Board {
@@ -22,6 +25,7 @@ pub struct Board {
     pub game_state: GameState,
     pub history: History,
     zr: ZobristRandoms,
+    nnue: Option<Nnue>,
 }
 
 impl Board {
@@ -32,6 +36,7 @@ impl Board {
             game_state: GameState::new(),
             history: History::new(),
             zr: ZobristRandoms::new(),
+            nnue: None,
         }
     }
 
@@ -105,12 +110,21 @@ impl Board {
         self.pieces[side][piece] ^= square.clone().to_bb();
         //self.bb_side[side] ^= square.to_bb();
     self.piece_list[square.0] = Pieces::NONE;
-        self.game_state.zobrist_key ^= self.zr.piece(side, piece, square);
 
         // Incremental updates
         // =============================================================
-        //let s = if side == Sides::WHITE { FLIP[square.0] } else { square };
-        //self.game_state.psqt[side] -= PSQT_MG[piece][s];
+        let s = psqt::index(side, square.0);
+        self.game_state.psqt_mg[side] -= PSQT_MG[piece][s];
+        self.game_state.psqt_eg[side] -= PSQT_EG[piece][s];
+        self.game_state.phase -= PHASE_VALUES[piece];
+        if let Some(nnue) = &mut self.nnue {
+            nnue.remove_piece(side, piece, square.0);
+        }
+        if piece == Pieces::PAWN {
+            self.game_state.pawn_key ^= self.zr.piece(side, piece, square.clone());
+        }
+
+        self.game_state.zobrist_key ^= self.zr.piece(side, piece, square);
     }
 
     // Put a piece onto the board, for the given side, piece, and square.
@@ -118,13 +132,21 @@ impl Board {
         self.pieces[side][piece] |= square.clone().to_bb();
         //self.bb_side[side] |= square.to_bb();
     self.piece_list[square.0] = piece;
-        self.game_state.zobrist_key ^= self.zr.piece(side, piece, square);
 
         // Incremental updates
         // =============================================================
-        //let flip = side == Sides::WHITE;
-        //let s = if flip { FLIP[square.0] } else { square };
-        //self.game_state.psqt[side] += PSQT_MG[piece][s];
+        let s = psqt::index(side, square.0);
+        self.game_state.psqt_mg[side] += PSQT_MG[piece][s];
+        self.game_state.psqt_eg[side] += PSQT_EG[piece][s];
+        self.game_state.phase += PHASE_VALUES[piece];
+        if let Some(nnue) = &mut self.nnue {
+            nnue.add_piece(side, piece, square.0);
+        }
+        if piece == Pieces::PAWN {
+            self.game_state.pawn_key ^= self.zr.piece(side, piece, square.clone());
+        }
+
+        self.game_state.zobrist_key ^= self.zr.piece(side, piece, square);
     }
 
     // Remove a piece from the from-square, and put it onto the to-square.
@@ -207,6 +229,22 @@ impl Board {
         key
     }
 
+    // Initialize the pawn hash. It only contains the pawns, so positions
+    // with the same pawn structure share their pawn evaluation.
+    pub fn init_pawn_key(&self) -> ZobristKey {
+        let mut key: u64 = 0;
+
+        for side in [Sides::WHITE, Sides::BLACK] {
+            let mut pawns = self.pieces[side][Pieces::PAWN];
+            while pawns.0 > 0 {
+                let square = BitBoard::next(&mut pawns);
+                key ^= self.zr.piece(side, Pieces::PAWN, square);
+            }
+        }
+
+        key
+    }
+
     pub fn print_board(&self) {
         // Characters for white and black pieces by piece type index
         let white_chars = ['K', 'Q', 'R', 'B', 'N', 'P'];
@@ -296,6 +334,41 @@ impl Board {
     fn init(&mut self) {
         self.piece_list = self.init_piece_list();
         self.game_state.zobrist_key = self.init_zobrist_key();
+        self.game_state.pawn_key = self.init_pawn_key();
+
+        let (psqt_mg, psqt_eg) = psqt::apply(self);
+        self.game_state.psqt_mg = psqt_mg;
+        self.game_state.psqt_eg = psqt_eg;
+        self.game_state.phase = psqt::game_phase(self);
+
+        if let Some(mut nnue) = self.nnue.take() {
+            nnue.refresh(self);
+            self.nnue = Some(nnue);
+        }
+    }
+
+    // Evaluate with the given network from now on, or with the handcrafted
+    // evaluation if there is none.
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(|network| Nnue::new(network, self));
+    }
+
+    pub fn nnue(&self) -> Option<&Nnue> {
+        self.nnue.as_ref()
+    }
+
+    // Keep the NNUE accumulator stack in step with the history: make() and
+    // make_null() push, unmake() and unmake_null() pop.
+    fn push_accumulator(&mut self) {
+        if let Some(nnue) = &mut self.nnue {
+            nnue.push();
+        }
+    }
+
+    fn pop_accumulator(&mut self) {
+        if let Some(nnue) = &mut self.nnue {
+            nnue.pop();
+        }
     }
 
     // pub fn check_board(self) {
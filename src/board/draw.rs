@@ -0,0 +1,182 @@
+use crate::{
+    BitBoard, Board,
+    defs::{MAX_MOVE_RULE, Pieces, Sides},
+    movegen::MoveGenerator,
+    movelist::MoveList,
+};
+
+// Draw detection. The search scores a position as a draw as soon as it
+// repeats once, because if repeating is good for one side, it can just
+// repeat again. The game itself is only drawn on a threefold repetition.
+impl Board {
+    // Count how often the current position occurred before. Only positions
+    // since the last irreversible move (capture or pawn move) can be the
+    // same, and only those with the same side to move, so every second
+    // game state within the halfmove clock is checked. A null move in the
+    // search isn't a real move: positions before it don't count.
+    pub fn repetitions(&self) -> usize {
+        let key = self.game_state.zobrist_key;
+        let count = self.history.len();
+        let reversible = (self.game_state.halfmove_clock as usize).min(count);
+
+        let mut repetitions = 0;
+        for back in 1..=reversible {
+            let state = self.history.get_ref(count - back);
+            if state.next_move.get_move() == 0 {
+                break;
+            }
+            if back % 2 == 0 && state.zobrist_key == key {
+                repetitions += 1;
+            }
+        }
+        repetitions
+    }
+
+    pub fn is_repetition(&self) -> bool {
+        self.repetitions() > 0
+    }
+
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetitions() >= 2
+    }
+
+    // Checkmate takes precedence over the fifty-move rule: a mate given
+    // with the hundredth halfmove still wins. Only then are the moves
+    // generated, so this is cheap in the search.
+    pub fn is_fifty_move_draw(&mut self, mg: &MoveGenerator) -> bool {
+        self.game_state.halfmove_clock >= MAX_MOVE_RULE && !self.is_checkmate(mg)
+    }
+
+    fn is_checkmate(&mut self, mg: &MoveGenerator) -> bool {
+        if !self.in_check(mg) {
+            return false;
+        }
+
+        let mut list = MoveList::new();
+        mg.generate_all_moves(self, &mut list);
+        for i in 0..list.len() {
+            if self.make(list.get_move(i), mg) {
+                self.unmake();
+                return false;
+            }
+        }
+        true
+    }
+
+    // Neither side can mate: KvK, KBvK, KNvK, or only bishops that all
+    // stand on squares of the same colour.
+    pub fn is_insufficient_material(&self) -> bool {
+        let w = &self.pieces[Sides::WHITE];
+        let b = &self.pieces[Sides::BLACK];
+        let heavy_or_pawns = w[Pieces::QUEEN]
+            | w[Pieces::ROOK]
+            | w[Pieces::PAWN]
+            | b[Pieces::QUEEN]
+            | b[Pieces::ROOK]
+            | b[Pieces::PAWN];
+        if heavy_or_pawns != BitBoard::EMPTY {
+            return false;
+        }
+
+        let knights = w[Pieces::KNIGHT] | b[Pieces::KNIGHT];
+        let bishops = w[Pieces::BISHOP] | b[Pieces::BISHOP];
+        let minors = (knights | bishops).pop_count();
+        if minors <= 1 {
+            return true;
+        }
+
+        let same_colour_bishops = (bishops & BitBoard::LIGHT_SQUARES) == BitBoard::EMPTY
+            || (bishops & !BitBoard::LIGHT_SQUARES) == BitBoard::EMPTY;
+        knights == BitBoard::EMPTY && same_colour_bishops
+    }
+
+    // Is the game drawn by the rules? Used for game adjudication; the
+    // search uses is_repetition() instead of a threefold repetition.
+    pub fn is_draw(&mut self, mg: &MoveGenerator) -> bool {
+        self.is_threefold_repetition()
+            || self.is_fifty_move_draw(mg)
+            || self.is_insufficient_material()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::movegen::MoveGenerator;
+
+    fn setup(fen: &str) -> Board {
+        let mut board = Board::new();
+        board.fen_read(Some(fen)).unwrap();
+        board
+    }
+
+    fn play(board: &mut Board, mg: &MoveGenerator, moves: &[&str]) {
+        for &move_str in moves {
+            let m = board
+                .legal_moves(mg)
+                .into_iter()
+                .find(|m| m.as_string() == move_str)
+                .unwrap();
+            board.make(m, mg);
+        }
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let knights = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+        play(&mut board, &mg, &knights);
+        assert!(board.is_repetition());
+        assert!(!board.is_threefold_repetition());
+        assert!(!board.is_draw(&mg));
+
+        play(&mut board, &mg, &knights);
+        assert!(board.is_threefold_repetition());
+        assert!(board.is_draw(&mg));
+    }
+
+    // Positions before a null move are not repetitions.
+    #[test]
+    fn repetition_stops_at_null_move() {
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+
+        play(&mut board, &mg, &["g1f3", "g8f6"]);
+        board.make_null();
+        board.make_null();
+        assert_eq!(board.repetitions(), 0);
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+        let mg = MoveGenerator::new();
+        let fen = "7k/8/6K1/8/8/8/8/1Q6 w - - 99 80";
+
+        let mut board = setup(fen);
+        play(&mut board, &mg, &["b1c1"]);
+        assert!(board.is_fifty_move_draw(&mg));
+        assert!(board.is_draw(&mg));
+
+        // Checkmate with the hundredth halfmove.
+        let mut board = setup(fen);
+        play(&mut board, &mg, &["b1b8"]);
+        assert!(!board.is_fifty_move_draw(&mg));
+        assert!(!board.is_draw(&mg));
+    }
+
+    #[test]
+    fn insufficient_material() {
+        for (fen, drawn) in [
+            ("4k3/8/8/8/8/8/8/3NK3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/3BK3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/2NNK3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1", false),
+        ] {
+            assert_eq!(setup(fen).is_insufficient_material(), drawn, "{fen}");
+        }
+    }
+}
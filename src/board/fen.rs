@@ -79,6 +79,73 @@ impl Board {
 
         result
     }
+
+    // Write the position as a FEN-string; the reverse of fen_read().
+    pub fn fen_write(&self) -> String {
+        let mut placement = String::new();
+        for rank in (Ranks::R1..=Ranks::R8).rev() {
+            let mut empty = 0;
+            for file in Files::A..=Files::H {
+                let square = rank * 8 + file;
+                match self.piece_char(square) {
+                    Some(c) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push(c);
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > Ranks::R1 {
+                placement.push(SPLITTER);
+            }
+        }
+
+        let gs = &self.game_state;
+        let color = if gs.side_to_move == Sides::WHITE {
+            'w'
+        } else {
+            'b'
+        };
+        let castling: String = [Castling::WK, Castling::WQ, Castling::BK, Castling::BQ]
+            .iter()
+            .zip(CASTLING_RIGHTS.chars())
+            .filter(|(right, _)| gs.castling & **right != BitBoard::EMPTY)
+            .map(|(_, c)| c)
+            .collect();
+        let castling = if castling.is_empty() {
+            DASH.to_string()
+        } else {
+            castling
+        };
+        let ep = match gs.en_passant {
+            Some(square) => SQUARE_NAME[square as usize].to_string(),
+            None => DASH.to_string(),
+        };
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement, color, castling, ep, gs.halfmove_clock, gs.fullmove_number
+        )
+    }
+
+    // The FEN character of the piece on a square, if there is one.
+    fn piece_char(&self, square: usize) -> Option<char> {
+        let bit = BitBoard(1) << BitBoard(square as u64);
+        for (side, offset) in [(Sides::BLACK, 0), (Sides::WHITE, NrOf::PIECE_TYPES)] {
+            for piece in 0..NrOf::PIECE_TYPES {
+                if self.pieces[side][piece] & bit != BitBoard::EMPTY {
+                    return LIST_OF_PIECES.chars().nth(offset + piece);
+                }
+            }
+        }
+        None
+    }
 }
 
 // ===== Private functions =====
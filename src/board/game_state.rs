@@ -1,6 +1,6 @@
 use crate::{BitBoard, movegen::bit_move::Move};
 
-use super::Side;
+use super::{Side, Sides};
 
 #[derive(Copy, Clone, PartialEq)]
 pub struct GameState {
@@ -11,6 +11,10 @@ pub struct GameState {
     pub side_to_move: Side,
     pub next_move: Move,
     pub zobrist_key: u64,
+    pub pawn_key: u64,
+    pub psqt_mg: [i32; Sides::BOTH],
+    pub psqt_eg: [i32; Sides::BOTH],
+    pub phase: i32,
 }
 
 impl GameState {
@@ -21,9 +25,12 @@ impl GameState {
             en_passant: None,
             halfmove_clock: 0,
             fullmove_number: 0,
-            //psqt: [0; Sides::BOTH],
             next_move: Move::new(0),
             zobrist_key: 0,
+            pawn_key: 0,
+            psqt_mg: [0; Sides::BOTH],
+            psqt_eg: [0; Sides::BOTH],
+            phase: 0,
         }
     }
 }
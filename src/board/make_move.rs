@@ -2,6 +2,7 @@ use crate::{
     BitBoard, Board, MoveGenerator,
     defs::{Castling, NrOf, Piece, Pieces, Side, Sides, Square},
     movegen::bit_move::Move,
+    movelist::MoveList,
 };
 
 // Castling Permissions Per Square
@@ -42,6 +43,7 @@ impl Board {
         let mut current_game_state = self.game_state;
         current_game_state.next_move = m;
         self.history.push(current_game_state);
+        self.push_accumulator();
 
         // Set "us" and "opponent"
         let us = self.game_state.side_to_move;
@@ -137,7 +139,7 @@ impl Board {
         //self.print_board();
 
         /*** Validating move: see if "us" is in check. If so, undo everything. ***/
-        let king_square = Square(self.pieces[us][Pieces::KING].0.trailing_zeros() as usize);
+        let king_square = self.king_square(us);
         let is_legal = !mg.square_attacked(self, opponent, &king_square);
         if !is_legal {
             //println!("Move is illegal, unmaking");
@@ -153,6 +155,23 @@ impl Board {
         // Report if the move was legal or not.
         is_legal
     }
+
+    // The legal moves in the position: the generated moves that make()
+    // accepts.
+    pub fn legal_moves(&mut self, mg: &MoveGenerator) -> Vec<Move> {
+        let mut list = MoveList::new();
+        mg.generate_all_moves(self, &mut list);
+
+        let mut moves = Vec::new();
+        for i in 0..list.len() {
+            let m = list.get_move(i);
+            if self.make(m, mg) {
+                self.unmake();
+                moves.push(m);
+            }
+        }
+        moves
+    }
 }
 
 /*** ================================================================================ ***/
@@ -164,6 +183,7 @@ impl Board {
     #[cfg_attr(not(debug_assertions), inline(always))]
     pub fn unmake(&mut self) {
         self.game_state = self.history.pop();
+        self.pop_accumulator();
 
         // Set "us" and "opponent"
         let us = self.game_state.side_to_move;
@@ -211,6 +231,37 @@ impl Board {
     }
 }
 
+/*** ================================================================================ ***/
+
+// A null move passes the turn to the opponent without moving a piece. It is
+// used by null-move pruning in the search. The game state is pushed onto the
+// history like a normal move, with a null move as "next_move", so
+// unmake_null() can restore it at once.
+impl Board {
+    pub fn make_null(&mut self) {
+        let mut current_game_state = self.game_state;
+        current_game_state.next_move = Move::null();
+        self.history.push(current_game_state);
+        self.push_accumulator();
+
+        if self.game_state.en_passant.is_some() {
+            self.clear_ep_square();
+        }
+        self.swap_side();
+    }
+
+    pub fn unmake_null(&mut self) {
+        self.game_state = self.history.pop();
+        self.pop_accumulator();
+    }
+
+    // Was the last move made on the board a null move?
+    pub fn last_move_null(&self) -> bool {
+        let count = self.history.len();
+        count > 0 && self.history.get_ref(count - 1).next_move.get_move() == 0
+    }
+}
+
 /*** Functions local to playmove.rs ====================================================== ***/
 
 // unamke() pops the entire game history from a list at the beginning. This
@@ -248,7 +299,9 @@ fn reverse_move(board: &mut Board, side: Side, piece: Piece, remove: Square, put
 
 fn check_incrementals(board: &Board) -> bool {
     let from_scratch_key = board.init_zobrist_key();
-    //let from_scratch_psqt = crate::evaluation::psqt::apply(board);
+    let from_scratch_pawn_key = board.init_pawn_key();
+    let from_scratch_psqt = crate::evaluate::psqt::apply(board);
+    let from_scratch_phase = crate::evaluate::psqt::game_phase(board);
     let mut result = true;
 
     // Waterfall: only report first error encountered and skip any others.
@@ -257,15 +310,25 @@ fn check_incrementals(board: &Board) -> bool {
         result = false;
     };
 
-    // if result && from_scratch_psqt.0 != board.game_state.psqt[Sides::WHITE] {
-    //     println!("Check Incrementals: Error in PSQT for white.");
-    //     result = false;
-    // };
+    if result && from_scratch_pawn_key != board.game_state.pawn_key {
+        println!("Check Incrementals: Error in pawn key.");
+        result = false;
+    };
+
+    if result && from_scratch_psqt.0 != board.game_state.psqt_mg {
+        println!("Check Incrementals: Error in middlegame PSQT.");
+        result = false;
+    };
+
+    if result && from_scratch_psqt.1 != board.game_state.psqt_eg {
+        println!("Check Incrementals: Error in endgame PSQT.");
+        result = false;
+    };
 
-    // if result && from_scratch_psqt.1 != board.game_state.psqt[Sides::BLACK] {
-    //     println!("Check Incrementals: Error in PSQT for black.");
-    //     result = false;
-    // };
+    if result && from_scratch_phase != board.game_state.phase {
+        println!("Check Incrementals: Error in game phase.");
+        result = false;
+    };
 
     result
 }
@@ -1,6 +1,6 @@
-use crate::Board;
+use crate::{BitBoard, Board, MoveGenerator};
 
-use super::{Ranks, Side, Sides, Square};
+use super::{Pieces, Ranks, Side, Sides, Square};
 
 impl Board {
     pub fn promotion_rank(side: Side) -> usize {
@@ -11,6 +11,25 @@ impl Board {
         }
     }
 
+    // The square the king of the given side is on.
+    pub fn king_square(&self, side: Side) -> Square {
+        Square(self.pieces[side][Pieces::KING].0.trailing_zeros() as usize)
+    }
+
+    // Is the side to move in check?
+    pub fn in_check(&self, mg: &MoveGenerator) -> bool {
+        let us = self.game_state.side_to_move;
+        mg.square_attacked(self, us ^ 1, &self.king_square(us))
+    }
+
+    // Does the side have any pieces besides its king and pawns? Without
+    // them, zugzwang is common.
+    pub fn has_non_pawn_material(&self, side: Side) -> bool {
+        let p = &self.pieces[side];
+        (p[Pieces::QUEEN] | p[Pieces::ROOK] | p[Pieces::BISHOP] | p[Pieces::KNIGHT])
+            != BitBoard::EMPTY
+    }
+
     // Compute if a given square is or isn't on the given rank.
     pub fn square_on_rank(square: &Square, rank: Square) -> bool {
         let start = (rank.0) * 8;
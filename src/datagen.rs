@@ -0,0 +1,355 @@
+use crate::board::Board;
+use crate::defs::{MAX_DEPTH, Pieces, Side, Sides};
+use crate::movegen::MoveGenerator;
+use crate::search::transposition::TranspositionTable;
+use crate::search::{self, MATE_BOUND, SearchInfo, SearchOptions, SearchSignals};
+use rand::Rng;
+use rand::rngs::ThreadRng;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Instant;
+
+// Self-play data generation, to train evaluation networks.
+//
+// Run as "rust-chess datagen <output> [games] [threads] [nodes]". Each
+// thread plays games of the engine against itself. A game starts with
+// RANDOM_PLIES random legal moves, so the games are all different; after
+// that, every move is searched with a fixed number of nodes. Games end by
+// the rules, or are adjudicated when both sides agree for a while that one
+// of them is winning, or that the position is a dead draw.
+//
+// Positions where the side to move is in check, where the best move is a
+// capture or a promotion, or where a mate was found, are not recorded:
+// their search score says little about the static position.
+//
+// Two files are written:
+//
+// - <output>.txt has one position per line, as "<fen> | <score> | <result>".
+//   The score is the search score in centipawns and the result is 1.0, 0.5
+//   or 0.0; both are from white's point of view.
+//
+// - <output>.bin has the same positions as 32-byte records in the
+//   "ChessBoard" format of bulletformat, which the bullet trainer reads.
+//   Everything is stored from the side to move's point of view; if that is
+//   black, the board is flipped vertically and the colours are swapped:
+//
+//   offset  size  contents
+//        0     8  occupancy bitboard (little-endian)
+//        8    16  the pieces, one nibble each in the order of the
+//                 occupied squares, low nibble first: 8 for the opponent's
+//                 pieces, plus 0 to 5 for pawn, knight, bishop, rook, queen
+//                 and king
+//       24     2  score (i16, little-endian)
+//       26     1  result: 0 loss, 1 draw, 2 win
+//       27     1  king square
+//       28     1  opponent's king square, flipped vertically
+//       29     3  unused (zero)
+
+const DEFAULT_GAMES: usize = 1000;
+const DEFAULT_NODES: u64 = 5000;
+const RANDOM_PLIES: usize = 8;
+const HASH_MB: usize = 16;
+
+// A game is won when the score is at least WIN_SCORE for one side for
+// WIN_PLIES plies in a row. It is drawn when the score stays within
+// DRAW_SCORE for DRAW_PLIES plies, from ply DRAW_MIN_PLY on, or when it
+// lasts MAX_PLIES.
+const WIN_SCORE: i32 = 1500;
+const WIN_PLIES: usize = 6;
+const DRAW_SCORE: i32 = 10;
+const DRAW_PLIES: usize = 12;
+const DRAW_MIN_PLY: usize = 80;
+const MAX_PLIES: usize = 400;
+
+const REPORT_INTERVAL: usize = 100;
+const RECORD_SIZE: usize = 32;
+
+struct Position {
+    fen: String,
+    // White, black, then pawns, knights, bishops, rooks, queens and kings
+    // of both sides.
+    bitboards: [u64; 8],
+    side_to_move: Side,
+    score: i16,
+}
+
+// A finished game. The result is from white's point of view.
+struct Game {
+    positions: Vec<Position>,
+    result: f32,
+}
+
+pub fn run(args: &[String]) {
+    let Some(output) = args.first() else {
+        eprintln!("Usage: rust-chess datagen <output> [games] [threads] [nodes]");
+        return;
+    };
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let (Some(games), Some(threads), Some(nodes)) = (
+        parse_arg(args, 1, DEFAULT_GAMES),
+        parse_arg(args, 2, cores),
+        parse_arg(args, 3, DEFAULT_NODES),
+    ) else {
+        eprintln!("Invalid arguments: {}", args.join(" "));
+        return;
+    };
+
+    let (mut text, mut binary) = match (
+        File::create(format!("{output}.txt")),
+        File::create(format!("{output}.bin")),
+    ) {
+        (Ok(text), Ok(binary)) => (BufWriter::new(text), BufWriter::new(binary)),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Can't create output files '{}': {}", output, e);
+            return;
+        }
+    };
+
+    println!("Playing {games} games on {threads} threads, {nodes} nodes per move");
+    let start = Instant::now();
+    let next_game = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut finished = 0;
+    let mut positions = 0;
+    let mut results = [0; 3];
+
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            let sender = sender.clone();
+            let next_game = &next_game;
+            s.spawn(move || play_games(next_game, games, nodes, sender));
+        }
+        drop(sender);
+
+        // Stop the workers on an error by dropping the receiver: they
+        // can't send their next game.
+        for game in receiver {
+            if let Err(e) = write_game(&mut text, &mut binary, &game) {
+                eprintln!("Can't write to '{}': {}", output, e);
+                break;
+            }
+
+            finished += 1;
+            positions += game.positions.len();
+            results[(game.result * 2.0) as usize] += 1;
+            if finished % REPORT_INTERVAL == 0 {
+                let seconds = start.elapsed().as_secs_f64().max(0.001);
+                println!(
+                    "Games {}/{}, positions {}, {:.0} positions/s",
+                    finished,
+                    games,
+                    positions,
+                    positions as f64 / seconds
+                );
+            }
+        }
+    });
+
+    if let Err(e) = text.flush().and_then(|_| binary.flush()) {
+        eprintln!("Can't write to '{}': {}", output, e);
+    }
+    println!(
+        "Finished {} games (+{} ={} -{}), {} positions in {:.1} s",
+        finished,
+        results[2],
+        results[1],
+        results[0],
+        positions,
+        start.elapsed().as_secs_f64()
+    );
+}
+
+// The argument at the given index, or the default if there is none. None
+// if the argument can't be parsed.
+fn parse_arg<T: std::str::FromStr>(args: &[String], index: usize, default: T) -> Option<T> {
+    match args.get(index) {
+        Some(arg) => arg.parse().ok(),
+        None => Some(default),
+    }
+}
+
+fn write_game(text: &mut impl Write, binary: &mut impl Write, game: &Game) -> std::io::Result<()> {
+    for position in &game.positions {
+        writeln!(
+            text,
+            "{} | {} | {:.1}",
+            position.fen, position.score, game.result
+        )?;
+        binary.write_all(&bulletformat(position, game.result))?;
+    }
+    Ok(())
+}
+
+// Play games until "games" games have been started by all threads together.
+fn play_games(next_game: &AtomicUsize, games: usize, nodes: u64, sender: Sender<Game>) {
+    let mg = MoveGenerator::new();
+    let mut tt = TranspositionTable::new(HASH_MB);
+    let options = SearchOptions::new();
+    let signals = SearchSignals::new(false);
+    let mut info = SearchInfo::new();
+    info.depth = MAX_DEPTH;
+    info.nodes = Some(nodes);
+    info.quiet = true;
+    let mut rng = rand::rng();
+
+    while next_game.fetch_add(1, Ordering::Relaxed) < games {
+        tt.clear();
+        let game = play_game(&mg, &mut tt, &info, &options, &signals, &mut rng);
+        if sender.send(game).is_err() {
+            return;
+        }
+    }
+}
+
+fn play_game(
+    mg: &MoveGenerator,
+    tt: &mut TranspositionTable,
+    info: &SearchInfo,
+    options: &SearchOptions,
+    signals: &SearchSignals,
+    rng: &mut ThreadRng,
+) -> Game {
+    let mut board = Board::new();
+    while !random_opening(&mut board, mg, rng) {}
+
+    let mut positions = Vec::new();
+    let mut winning = [0; Sides::BOTH];
+    let mut drawn = 0;
+
+    let result = 'game: {
+        for ply in 0..MAX_PLIES {
+            let side = board.game_state.side_to_move;
+            let in_check = board.in_check(mg);
+            if board.legal_moves(mg).is_empty() {
+                break 'game match (in_check, side) {
+                    (false, _) => 0.5,
+                    (true, Sides::WHITE) => 0.0,
+                    (true, _) => 1.0,
+                };
+            }
+            if board.is_draw(mg) {
+                break 'game 0.5;
+            }
+
+            let result = search::search_position(&mut board, info, options, mg, tt, signals, 1);
+            let Some(best_move) = result.best_move else {
+                break 'game 0.5;
+            };
+            let score = result.score;
+
+            // Adjudicate.
+            for s in [Sides::WHITE, Sides::BLACK] {
+                let winning_score = if s == side { score } else { -score };
+                winning[s] = if winning_score >= WIN_SCORE {
+                    winning[s] + 1
+                } else {
+                    0
+                };
+            }
+            if winning[Sides::WHITE] >= WIN_PLIES {
+                break 'game 1.0;
+            }
+            if winning[Sides::BLACK] >= WIN_PLIES {
+                break 'game 0.0;
+            }
+            drawn = if ply >= DRAW_MIN_PLY && score.abs() <= DRAW_SCORE {
+                drawn + 1
+            } else {
+                0
+            };
+            if drawn >= DRAW_PLIES {
+                break 'game 0.5;
+            }
+
+            if !in_check && best_move.is_quiet() && score.abs() < MATE_BOUND {
+                let white_score = if side == Sides::WHITE { score } else { -score };
+                positions.push(Position::new(&board, white_score));
+            }
+            board.make(best_move, mg);
+        }
+        0.5
+    };
+
+    Game { positions, result }
+}
+
+// Set up the start position and play RANDOM_PLIES random moves. Returns
+// false if the game ended during the opening.
+fn random_opening(board: &mut Board, mg: &MoveGenerator, rng: &mut ThreadRng) -> bool {
+    let _ = board.fen_read(None);
+    for _ in 0..RANDOM_PLIES {
+        let moves = board.legal_moves(mg);
+        if moves.is_empty() {
+            return false;
+        }
+        board.make(moves[rng.random_range(0..moves.len())], mg);
+    }
+    !board.legal_moves(mg).is_empty()
+}
+
+impl Position {
+    fn new(board: &Board, score: i32) -> Self {
+        let both = |piece: usize| {
+            (board.pieces[Sides::WHITE][piece] | board.pieces[Sides::BLACK][piece]).0
+        };
+        Self {
+            fen: board.fen_write(),
+            bitboards: [
+                board.occupancy(Sides::WHITE).0,
+                board.occupancy(Sides::BLACK).0,
+                both(Pieces::PAWN),
+                both(Pieces::KNIGHT),
+                both(Pieces::BISHOP),
+                both(Pieces::ROOK),
+                both(Pieces::QUEEN),
+                both(Pieces::KING),
+            ],
+            side_to_move: board.game_state.side_to_move,
+            score: score.clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+        }
+    }
+}
+
+// Encode a position as a bulletformat record (see the top of this file).
+fn bulletformat(position: &Position, result: f32) -> [u8; RECORD_SIZE] {
+    let mut bitboards = position.bitboards;
+    let mut score = position.score;
+    let mut result = result;
+    if position.side_to_move == Sides::BLACK {
+        for bb in bitboards.iter_mut() {
+            *bb = bb.swap_bytes();
+        }
+        bitboards.swap(0, 1);
+        score = score.saturating_neg();
+        result = 1.0 - result;
+    }
+
+    let occupancy = bitboards[0] | bitboards[1];
+    let mut pieces = [0u8; 16];
+    let mut occupied = occupancy;
+    let mut i = 0;
+    while occupied > 0 {
+        let bit = occupied & occupied.wrapping_neg();
+        occupied &= occupied - 1;
+        let colour = if bitboards[1] & bit != 0 { 8 } else { 0 };
+        let piece = bitboards[2..]
+            .iter()
+            .position(|bb| bb & bit != 0)
+            .unwrap_or(0) as u8;
+        pieces[i / 2] |= (colour | piece) << (4 * (i % 2));
+        i += 1;
+    }
+
+    let kings = bitboards[7];
+    let mut record = [0; RECORD_SIZE];
+    record[0..8].copy_from_slice(&occupancy.to_le_bytes());
+    record[8..24].copy_from_slice(&pieces);
+    record[24..26].copy_from_slice(&score.to_le_bytes());
+    record[26] = (result * 2.0) as u8;
+    record[27] = (bitboards[0] & kings).trailing_zeros() as u8;
+    record[28] = (bitboards[1] & kings).trailing_zeros() as u8 ^ 56;
+    record
+}
@@ -27,6 +27,7 @@ pub const FEN_START_POSITION: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN
 pub const FEN_KIWIPETE_POSITION: &str =
     "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
 pub const MAX_DEPTH: i8 = 64;
+pub const MAX_PLY: usize = 128;
 pub struct NrOf;
 impl NrOf {
     pub const PIECE_TYPES: usize = 6;
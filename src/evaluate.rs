@@ -1,43 +1,54 @@
+pub mod king_safety;
+pub mod mobility;
+pub mod nnue;
+pub mod pawns;
+pub mod psqt;
+
 use crate::board::Board;
-use crate::defs::{Piece, Pieces, Sides};
-pub fn evaluate_position(board: &mut Board) -> i32 {
-    count_material(board)
-}
+use crate::defs::{NrOf, Sides};
+use crate::movegen::MoveGenerator;
+use king_safety::evaluate_king_safety;
+use mobility::evaluate_mobility;
+use pawns::{PawnHashTable, evaluate_pawns};
+use psqt::MAX_PHASE;
+
+// Material values indexed by piece type, used by the search (for example
+// in delta pruning). The king can't be captured, so it is worth nothing.
+pub const PIECE_VALUES: [i32; NrOf::PIECE_TYPES + 1] = [0, 900, 500, 300, 300, 100, 0];
+
+// The score of the position from the point of view of the side to move. If
+// the board has an NNUE network, that network is used; otherwise the
+// handcrafted evaluation.
+pub fn evaluate_position(
+    board: &mut Board,
+    mg: &MoveGenerator,
+    pawn_table: &mut PawnHashTable,
+) -> i32 {
+    if let Some(nnue) = board.nnue() {
+        return nnue.evaluate(board.game_state.side_to_move);
+    }
+
+    let (pawns_mg, pawns_eg) = evaluate_pawns(board, pawn_table);
+    let mobility = evaluate_mobility(board, mg);
+    let king_safety = evaluate_king_safety(board, &mobility);
 
-fn count_material(board: &mut Board) -> i32 {
-    let mut material_score: i32 = 0;
-    material_score += 100
-        * (board.pieces[Sides::WHITE][Pieces::PAWN].pop_count() as i32
-            - board.pieces[Sides::BLACK][Pieces::PAWN].pop_count() as i32);
-    material_score += 300
-        * (board.pieces[Sides::WHITE][Pieces::KNIGHT].pop_count() as i32
-            - board.pieces[Sides::BLACK][Pieces::KNIGHT].pop_count() as i32);
-    material_score += 300
-        * (board.pieces[Sides::WHITE][Pieces::BISHOP].pop_count() as i32
-            - board.pieces[Sides::BLACK][Pieces::BISHOP].pop_count() as i32);
-    material_score += 500
-        * (board.pieces[Sides::WHITE][Pieces::ROOK].pop_count() as i32
-            - board.pieces[Sides::BLACK][Pieces::ROOK].pop_count() as i32);
-    material_score += 900
-        * (board.pieces[Sides::WHITE][Pieces::QUEEN].pop_count() as i32
-            - board.pieces[Sides::BLACK][Pieces::QUEEN].pop_count() as i32);
-    material_score += 20000
-        * (board.pieces[Sides::WHITE][Pieces::KING].pop_count() as i32
-            - board.pieces[Sides::BLACK][Pieces::KING].pop_count() as i32);
-    match board.game_state.side_to_move {
-        Sides::WHITE => material_score,
-        Sides::BLACK => -material_score,
+    let gs = &board.game_state;
+    let middlegame =
+        gs.psqt_mg[Sides::WHITE] - gs.psqt_mg[Sides::BLACK] + pawns_mg + mobility.mg + king_safety;
+    let endgame = gs.psqt_eg[Sides::WHITE] - gs.psqt_eg[Sides::BLACK] + pawns_eg + mobility.eg;
+    let score = taper(middlegame, endgame, gs.phase);
+
+    match gs.side_to_move {
+        Sides::WHITE => score,
+        Sides::BLACK => -score,
         _ => 0,
     }
 }
 
-// fn mobility(board: &mut Board) -> i32 {
-//     let mut mobility_score: i32 = 0;
-//     move_gen.generate_all_moves(board, &mut move_list);
-//     mobility_score += move_list.len() as i32;
-//     match board.game_state.side_to_move {
-//         Sides::WHITE => mobility_score,
-//         Sides::BLACK => -mobility_score,
-//         _ => 0
-//     }
-// }
+// Blend the middlegame and endgame scores according to the game phase. In
+// the opening, the score is the middlegame score; as pieces are traded, the
+// endgame score gradually takes over.
+fn taper(mg: i32, eg: i32, phase: i32) -> i32 {
+    let phase = phase.clamp(0, MAX_PHASE);
+    (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
@@ -0,0 +1,100 @@
+use super::mobility::Mobility;
+use crate::BitBoard;
+use crate::board::Board;
+use crate::defs::{Pieces, Side, Sides};
+
+// King safety, for the middlegame only: in the endgame, the king is an
+// active piece and there are too few attackers left to threaten it.
+//
+// A king is safer behind a shield of its own pawns, and less safe next to
+// open files, where enemy rooks and queens can reach it. Attacks on the
+// king zone are counted during the mobility evaluation; a single attacker
+// is rarely dangerous, so the penalty grows with the number of attackers.
+
+pub const SHIELD_CLOSE: i32 = 12;
+pub const SHIELD_FAR: i32 = 6;
+pub const OPEN_FILE: i32 = -25;
+pub const SEMI_OPEN_FILE: i32 = -12;
+
+// Percentage of the attack weight that counts, by number of attackers.
+const ATTACKER_SCALE: [i32; 8] = [0, 0, 50, 75, 88, 94, 97, 99];
+
+// The middlegame king safety score, from white's point of view.
+pub fn evaluate_king_safety(board: &Board, mobility: &Mobility) -> i32 {
+    let white = king_shelter(board, Sides::WHITE) - king_attack(mobility, Sides::BLACK);
+    let black = king_shelter(board, Sides::BLACK) - king_attack(mobility, Sides::WHITE);
+    white - black
+}
+
+// The penalty for the attacks of "attacker" on the enemy king zone.
+fn king_attack(mobility: &Mobility, attacker: Side) -> i32 {
+    mobility.king_attack_weight[attacker] * attacker_scale(mobility.king_attackers[attacker]) / 100
+}
+
+// The percentage of the king attack weight that counts.
+pub fn attacker_scale(attackers: i32) -> i32 {
+    ATTACKER_SCALE[attackers.min(7) as usize]
+}
+
+// Pawn shield and open files on the king's file and the files next to it.
+fn king_shelter(board: &Board, side: Side) -> i32 {
+    let shelter = classify_shelter(board, side);
+    shelter.close * SHIELD_CLOSE
+        + shelter.far * SHIELD_FAR
+        + shelter.open * OPEN_FILE
+        + shelter.semi_open * SEMI_OPEN_FILE
+}
+
+// The number of files next to (and on) the king with a shield pawn one or
+// two squares in front of the king, with no pawns at all, or with enemy
+// pawns only.
+pub struct Shelter {
+    pub close: i32,
+    pub far: i32,
+    pub open: i32,
+    pub semi_open: i32,
+}
+
+pub fn classify_shelter(board: &Board, side: Side) -> Shelter {
+    let king_square = board.king_square(side).0 as i32;
+    let king_file = king_square % 8;
+    let own_pawns = board.pieces[side][Pieces::PAWN].0;
+    let their_pawns = board.pieces[side ^ 1][Pieces::PAWN].0;
+
+    // The shield squares one and two ranks in front of the king.
+    let forward = if side == Sides::WHITE { 8 } else { -8 };
+    let close = king_square + forward;
+    let far = king_square + 2 * forward;
+
+    let mut shelter = Shelter {
+        close: 0,
+        far: 0,
+        open: 0,
+        semi_open: 0,
+    };
+    for file in (king_file - 1).max(0)..=(king_file + 1).min(7) {
+        let file_mask = BitBoard::A_FILE.0 << file;
+        let file_offset = file - king_file;
+
+        if own_pawns & file_mask == 0 {
+            if their_pawns & file_mask == 0 {
+                shelter.open += 1;
+            } else {
+                shelter.semi_open += 1;
+            }
+        } else if is_pawn_on(own_pawns, close, file_offset) {
+            shelter.close += 1;
+        } else if is_pawn_on(own_pawns, far, file_offset) {
+            shelter.far += 1;
+        }
+    }
+
+    shelter
+}
+
+// Is there a pawn on the square "offset" files away from the given one?
+// Squares off the board never hold a pawn.
+fn is_pawn_on(pawns: u64, square: i32, offset: i32) -> bool {
+    let square = square + offset;
+    (0..64).contains(&square) && pawns & (1 << square) != 0
+}
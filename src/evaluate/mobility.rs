@@ -0,0 +1,100 @@
+use super::pawns::pawn_attacks;
+use crate::BitBoard;
+use crate::board::Board;
+use crate::defs::{NrOf, Pieces, Side, Sides, Square};
+use crate::movegen::MoveGenerator;
+
+// Mobility: pieces that can go to many squares are worth more. The attacks
+// of each piece are looked up in the move generator's tables instead of
+// generating moves. Squares occupied by our own pieces or attacked by enemy
+// pawns don't count, because a piece can't go there safely.
+//
+// While the attacks are known, the attacks on the enemy king zone (the king
+// square and the squares around it) are counted for the king safety term.
+
+// Bonus per safe square, indexed by piece type, as (middlegame, endgame).
+// The score is relative to an average number of squares for that piece, so
+// a piece with few squares gets a penalty.
+pub const MOBILITY_MG: [i32; NrOf::PIECE_TYPES] = [0, 1, 2, 4, 4, 0];
+pub const MOBILITY_EG: [i32; NrOf::PIECE_TYPES] = [0, 2, 4, 4, 4, 0];
+pub const MOBILITY_AVERAGE: [i32; NrOf::PIECE_TYPES] = [0, 12, 7, 6, 4, 0];
+
+// Weight of one attack on a square of the enemy king zone, indexed by the
+// type of the attacking piece.
+pub const KING_ATTACK_WEIGHT: [i32; NrOf::PIECE_TYPES] = [0, 40, 25, 15, 15, 0];
+
+const MOBILE_PIECES: [usize; 4] = [Pieces::QUEEN, Pieces::ROOK, Pieces::BISHOP, Pieces::KNIGHT];
+
+// The result of the mobility evaluation. The king attack values are
+// indexed by the attacking side.
+pub struct Mobility {
+    pub mg: i32,
+    pub eg: i32,
+    pub king_attackers: [i32; Sides::BOTH],
+    pub king_attack_weight: [i32; Sides::BOTH],
+}
+
+// Evaluate mobility for both sides, from white's point of view.
+pub fn evaluate_mobility(board: &Board, mg: &MoveGenerator) -> Mobility {
+    let mut mobility = Mobility {
+        mg: 0,
+        eg: 0,
+        king_attackers: [0; Sides::BOTH],
+        king_attack_weight: [0; Sides::BOTH],
+    };
+
+    for side in [Sides::WHITE, Sides::BLACK] {
+        let sign = if side == Sides::WHITE { 1 } else { -1 };
+        piece_mobility(board, mg, side, |piece, squares, zone_attacks| {
+            mobility.mg += sign * MOBILITY_MG[piece] * (squares - MOBILITY_AVERAGE[piece]);
+            mobility.eg += sign * MOBILITY_EG[piece] * (squares - MOBILITY_AVERAGE[piece]);
+
+            if zone_attacks > 0 {
+                mobility.king_attackers[side] += 1;
+                mobility.king_attack_weight[side] += KING_ATTACK_WEIGHT[piece] * zone_attacks;
+            }
+        });
+    }
+
+    mobility
+}
+
+// Call "f" for each mobile piece of one side, with the piece type, the
+// number of safe squares it attacks and the number of attacked squares in
+// the enemy king zone.
+pub fn piece_mobility(
+    board: &Board,
+    mg: &MoveGenerator,
+    side: Side,
+    mut f: impl FnMut(usize, i32, i32),
+) {
+    let occupancy = board.occupancy(Sides::BOTH);
+    let their_pawns = board.pieces[side ^ 1][Pieces::PAWN].0;
+    let unsafe_squares = board.occupancy(side) | BitBoard(pawn_attacks(side ^ 1, their_pawns));
+    let king_zone = king_zone(board, mg, side ^ 1);
+
+    for piece in MOBILE_PIECES {
+        let mut pieces = board.pieces[side][piece];
+        while pieces.0 > 0 {
+            let square = BitBoard::next(&mut pieces);
+            let attacks = attacks(mg, piece, &square, occupancy);
+            let squares = (attacks & !unsafe_squares).pop_count() as i32;
+            let zone_attacks = (attacks & king_zone).pop_count() as i32;
+            f(piece, squares, zone_attacks);
+        }
+    }
+}
+
+fn attacks(mg: &MoveGenerator, piece: usize, square: &Square, occupancy: BitBoard) -> BitBoard {
+    if piece == Pieces::KNIGHT {
+        mg.get_knight_attacks(square)
+    } else {
+        mg.get_slider_attacks(piece, square, occupancy)
+    }
+}
+
+// The king square and the squares around it.
+fn king_zone(board: &Board, mg: &MoveGenerator, side: Side) -> BitBoard {
+    let king_square = board.king_square(side);
+    mg.get_king_attacks(&king_square) | king_square.to_bb()
+}
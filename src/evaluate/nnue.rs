@@ -0,0 +1,236 @@
+use crate::BitBoard;
+use crate::board::Board;
+use crate::defs::{NrOf, Piece, Side, Sides};
+use simd::Level;
+use std::sync::Arc;
+
+pub mod simd;
+
+// NNUE: an efficiently updatable neural network evaluation.
+//
+// The network has 768 inputs: one for each combination of side, piece type
+// and square. They feed a hidden layer of HIDDEN neurons, the accumulator.
+// There are two accumulators, one from each side's perspective: the black
+// one sees the board mirrored, with the colours swapped. The output layer
+// takes the side to move's accumulator followed by the other one, activated
+// with SCReLU (squared clipped ReLU), and produces a single score.
+//
+// The inputs change only a few at a time when a move is made, so instead of
+// computing the hidden layer from scratch, the weights of the inputs that
+// changed are added to or subtracted from the accumulators. The board keeps
+// a stack of accumulators that grows and shrinks together with its history;
+// unmaking a move just pops the stack.
+//
+// Weights are stored as little-endian i16 values in the order: feature
+// weights (768 x HIDDEN), feature biases (HIDDEN), output weights
+// (2 x HIDDEN) and the output bias. This is the format written by the
+// "bullet" trainer for this architecture. Files may be padded at the end.
+//
+// The accumulator updates and the output layer use SIMD instructions when
+// the CPU supports them (see simd.rs).
+
+pub const INPUTS: usize = NrOf::PIECE_TYPES * NrOf::SQUARES * Sides::BOTH;
+pub const HIDDEN: usize = 128;
+
+// Quantisation: the feature layer is scaled by QA, the output layer by QB.
+// SCALE converts the network output into centipawns.
+const QA: i16 = 255;
+const QB: i64 = 64;
+const SCALE: i64 = 400;
+
+// Keep network scores well away from mate scores.
+const MAX_EVAL: i32 = 30_000;
+
+// The network orders the pieces P, N, B, R, Q, K; the engine K, Q, R, B,
+// N, P.
+const NETWORK_PIECE: [usize; NrOf::PIECE_TYPES] = [5, 4, 3, 2, 1, 0];
+
+pub struct Network {
+    pub feature_weights: Vec<i16>,
+    pub feature_bias: Vec<i16>,
+    pub output_weights: Vec<i16>,
+    pub output_bias: i16,
+}
+
+impl Network {
+    // The number of i16 values in a network file, without padding.
+    const VALUES: usize = INPUTS * HIDDEN + HIDDEN + 2 * HIDDEN + 1;
+
+    pub fn load(path: &str) -> Result<Arc<Self>, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Can't read '{}': {}", path, e))?;
+        Self::from_bytes(&bytes).map(Arc::new)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < Self::VALUES * 2 {
+            return Err(format!(
+                "Network file is too small: {} bytes, expected {}",
+                bytes.len(),
+                Self::VALUES * 2
+            ));
+        }
+
+        let mut values = bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]));
+        let mut take = |n: usize| values.by_ref().take(n).collect::<Vec<i16>>();
+
+        let feature_weights = take(INPUTS * HIDDEN);
+        let feature_bias = take(HIDDEN);
+        let output_weights = take(2 * HIDDEN);
+        let output_bias = take(1)[0];
+
+        Ok(Self {
+            feature_weights,
+            feature_bias,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    // The weights connecting one input to the hidden layer.
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * HIDDEN..(feature + 1) * HIDDEN]
+    }
+
+    // Run the output layer on the accumulators of the side to move ("us")
+    // and the opponent ("them").
+    fn output(&self, us: &[i16; HIDDEN], them: &[i16; HIDDEN], level: Level) -> i32 {
+        let (us_weights, them_weights) = self.output_weights.split_at(HIDDEN);
+        let output = simd::screlu_dot(level, us, us_weights, QA).wrapping_add(simd::screlu_dot(
+            level,
+            them,
+            them_weights,
+            QA,
+        ));
+
+        // SCReLU squares the QA scaling; divide one of them out before
+        // adding the bias, which is scaled by QA * QB. Scale in 64 bits so
+        // that a badly trained network can't overflow.
+        let qa = QA as i64;
+        let output = output as i64 / qa + self.output_bias as i64;
+        (output * SCALE / (qa * QB)) as i32
+    }
+}
+
+// The index of the input for a piece, as seen from one side's perspective.
+fn feature(perspective: Side, side: Side, piece: Piece, square: usize) -> usize {
+    let (colour, square) = if perspective == Sides::WHITE {
+        (side, square)
+    } else {
+        (side ^ 1, square ^ 56)
+    };
+    (colour * NrOf::PIECE_TYPES + NETWORK_PIECE[piece]) * NrOf::SQUARES + square
+}
+
+#[derive(Copy, Clone)]
+#[repr(C, align(64))]
+pub struct Accumulator {
+    pub values: [[i16; HIDDEN]; Sides::BOTH],
+}
+
+impl Accumulator {
+    // Compute both perspectives from scratch.
+    fn refresh(network: &Network, board: &Board, level: Level) -> Self {
+        let mut acc = Self {
+            values: [[0; HIDDEN]; Sides::BOTH],
+        };
+        for perspective in [Sides::WHITE, Sides::BLACK] {
+            acc.values[perspective].copy_from_slice(&network.feature_bias);
+        }
+
+        for side in [Sides::WHITE, Sides::BLACK] {
+            for piece in 0..NrOf::PIECE_TYPES {
+                let mut bb = board.pieces[side][piece];
+                while bb.0 > 0 {
+                    let square = BitBoard::next(&mut bb).0;
+                    acc.add(network, side, piece, square, level);
+                }
+            }
+        }
+
+        acc
+    }
+
+    fn add(&mut self, network: &Network, side: Side, piece: Piece, square: usize, level: Level) {
+        for perspective in [Sides::WHITE, Sides::BLACK] {
+            let weights = network.weights(feature(perspective, side, piece, square));
+            simd::add(level, &mut self.values[perspective], weights);
+        }
+    }
+
+    fn remove(&mut self, network: &Network, side: Side, piece: Piece, square: usize, level: Level) {
+        for perspective in [Sides::WHITE, Sides::BLACK] {
+            let weights = network.weights(feature(perspective, side, piece, square));
+            simd::sub(level, &mut self.values[perspective], weights);
+        }
+    }
+}
+
+// The network in use and the accumulator stack of one board. The top of the
+// stack belongs to the current position.
+#[derive(Clone)]
+pub struct Nnue {
+    network: Arc<Network>,
+    stack: Vec<Accumulator>,
+    level: Level,
+}
+
+impl Nnue {
+    pub fn new(network: Arc<Network>, board: &Board) -> Self {
+        let mut nnue = Self {
+            network,
+            stack: Vec::new(),
+            level: simd::level(),
+        };
+        nnue.refresh(board);
+        nnue
+    }
+
+    // Throw away the stack and compute the accumulator of the current
+    // position from scratch.
+    pub fn refresh(&mut self, board: &Board) {
+        self.stack.clear();
+        self.stack
+            .push(Accumulator::refresh(&self.network, board, self.level));
+    }
+
+    // Called when a move is made: the new position starts with a copy of
+    // the current accumulator, which is then updated.
+    pub fn push(&mut self) {
+        let top = *self.top();
+        self.stack.push(top);
+    }
+
+    pub fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    pub fn add_piece(&mut self, side: Side, piece: Piece, square: usize) {
+        let network = &self.network;
+        if let Some(top) = self.stack.last_mut() {
+            top.add(network, side, piece, square, self.level);
+        }
+    }
+
+    pub fn remove_piece(&mut self, side: Side, piece: Piece, square: usize) {
+        let network = &self.network;
+        if let Some(top) = self.stack.last_mut() {
+            top.remove(network, side, piece, square, self.level);
+        }
+    }
+
+    pub fn top(&self) -> &Accumulator {
+        self.stack.last().expect("NNUE accumulator stack is empty")
+    }
+
+    // The evaluation from the point of view of the side to move.
+    pub fn evaluate(&self, side_to_move: Side) -> i32 {
+        let acc = self.top();
+        let us = &acc.values[side_to_move];
+        let them = &acc.values[side_to_move ^ 1];
+        self.network
+            .output(us, them, self.level)
+            .clamp(-MAX_EVAL, MAX_EVAL)
+    }
+}
@@ -0,0 +1,380 @@
+use super::HIDDEN;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// Vectorized versions of the NNUE inner loops: adding or subtracting a
+// column of weights to an accumulator, and the SCReLU dot product of the
+// output layer. The instruction set is detected once, at runtime, so one
+// binary runs on every x86-64 CPU and uses the widest vectors available.
+// Other platforms use the scalar code.
+//
+// All versions produce exactly the same results: accumulators wrap on
+// overflow like the vector instructions do, and the dot product is summed
+// in 32-bit integers with wrapping, so the order of the additions doesn't
+// matter.
+
+// AVX-512 handles 32 values at a time; the hidden layer must be a multiple
+// of that.
+const _: () = assert!(HIDDEN.is_multiple_of(32));
+
+// Ordered from slow to fast.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    Scalar,
+    Sse41,
+    Avx2,
+    Avx512,
+}
+
+// A slower level can be chosen, to compare the versions.
+static LIMIT: AtomicU8 = AtomicU8::new(Level::Avx512 as u8);
+
+// The fastest level this CPU supports, up to the limit.
+pub fn level() -> Level {
+    static LEVEL: OnceLock<Level> = OnceLock::new();
+    let limit = match LIMIT.load(Ordering::Relaxed) {
+        0 => Level::Scalar,
+        1 => Level::Sse41,
+        2 => Level::Avx2,
+        _ => Level::Avx512,
+    };
+    let detected = *LEVEL.get_or_init(detect);
+    detected.min(limit)
+}
+
+// Network evaluations set up after this use at most the given level.
+pub fn set_limit(limit: Level) {
+    LIMIT.store(limit as u8, Ordering::Relaxed);
+}
+
+fn detect() -> Level {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512bw") {
+            return Level::Avx512;
+        }
+        if is_x86_feature_detected!("avx2") {
+            return Level::Avx2;
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            return Level::Sse41;
+        }
+    }
+    Level::Scalar
+}
+
+// acc += weights
+pub fn add(level: Level, acc: &mut [i16; HIDDEN], weights: &[i16]) {
+    assert_eq!(weights.len(), HIDDEN);
+    match level {
+        // SAFETY: the level is only ever one the CPU supports.
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx512 => unsafe { add_avx512(acc, weights) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { add_avx2(acc, weights) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse41 => unsafe { add_sse41(acc, weights) },
+        _ => {
+            for (a, w) in acc.iter_mut().zip(weights) {
+                *a = a.wrapping_add(*w);
+            }
+        }
+    }
+}
+
+// acc -= weights
+pub fn sub(level: Level, acc: &mut [i16; HIDDEN], weights: &[i16]) {
+    assert_eq!(weights.len(), HIDDEN);
+    match level {
+        // SAFETY: the level is only ever one the CPU supports.
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx512 => unsafe { sub_avx512(acc, weights) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { sub_avx2(acc, weights) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse41 => unsafe { sub_sse41(acc, weights) },
+        _ => {
+            for (a, w) in acc.iter_mut().zip(weights) {
+                *a = a.wrapping_sub(*w);
+            }
+        }
+    }
+}
+
+// The sum of SCReLU(input) * weight, where SCReLU(x) = clamp(x, 0, qa)^2.
+pub fn screlu_dot(level: Level, input: &[i16; HIDDEN], weights: &[i16], qa: i16) -> i32 {
+    assert_eq!(weights.len(), HIDDEN);
+    match level {
+        // SAFETY: the level is only ever one the CPU supports.
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx512 => unsafe { screlu_dot_avx512(input, weights, qa) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { screlu_dot_avx2(input, weights, qa) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse41 => unsafe { screlu_dot_sse41(input, weights, qa) },
+        _ => input.iter().zip(weights).fold(0i32, |sum, (&x, &w)| {
+            let x = x.clamp(0, qa) as i32;
+            sum.wrapping_add(x * x * w as i32)
+        }),
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512bw")]
+unsafe fn add_avx512(acc: &mut [i16; HIDDEN], weights: &[i16]) {
+    for i in (0..HIDDEN).step_by(32) {
+        unsafe {
+            let a = acc.as_mut_ptr().add(i) as *mut __m512i;
+            let w = _mm512_loadu_si512(weights.as_ptr().add(i) as *const __m512i);
+            _mm512_storeu_si512(a, _mm512_add_epi16(_mm512_loadu_si512(a), w));
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512bw")]
+unsafe fn sub_avx512(acc: &mut [i16; HIDDEN], weights: &[i16]) {
+    for i in (0..HIDDEN).step_by(32) {
+        unsafe {
+            let a = acc.as_mut_ptr().add(i) as *mut __m512i;
+            let w = _mm512_loadu_si512(weights.as_ptr().add(i) as *const __m512i);
+            _mm512_storeu_si512(a, _mm512_sub_epi16(_mm512_loadu_si512(a), w));
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512bw")]
+unsafe fn screlu_dot_avx512(input: &[i16; HIDDEN], weights: &[i16], qa: i16) -> i32 {
+    let zero = _mm512_setzero_si512();
+    let max = _mm512_set1_epi16(qa);
+    let mut sum = _mm512_setzero_si512();
+
+    for i in (0..HIDDEN).step_by(32) {
+        unsafe {
+            let x = _mm512_loadu_si512(input.as_ptr().add(i) as *const __m512i);
+            let w = _mm512_loadu_si512(weights.as_ptr().add(i) as *const __m512i);
+            let x = _mm512_min_epi16(_mm512_max_epi16(x, zero), max);
+
+            // Widen to 32 bits: the squares don't fit in 16.
+            let x_lo = _mm512_cvtepi16_epi32(_mm512_castsi512_si256(x));
+            let x_hi = _mm512_cvtepi16_epi32(_mm512_extracti64x4_epi64::<1>(x));
+            let w_lo = _mm512_cvtepi16_epi32(_mm512_castsi512_si256(w));
+            let w_hi = _mm512_cvtepi16_epi32(_mm512_extracti64x4_epi64::<1>(w));
+
+            let lo = _mm512_mullo_epi32(_mm512_mullo_epi32(x_lo, x_lo), w_lo);
+            let hi = _mm512_mullo_epi32(_mm512_mullo_epi32(x_hi, x_hi), w_hi);
+            sum = _mm512_add_epi32(sum, _mm512_add_epi32(lo, hi));
+        }
+    }
+
+    _mm512_reduce_add_epi32(sum)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn add_avx2(acc: &mut [i16; HIDDEN], weights: &[i16]) {
+    for i in (0..HIDDEN).step_by(16) {
+        unsafe {
+            let a = acc.as_mut_ptr().add(i) as *mut __m256i;
+            let w = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+            _mm256_storeu_si256(a, _mm256_add_epi16(_mm256_loadu_si256(a), w));
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn sub_avx2(acc: &mut [i16; HIDDEN], weights: &[i16]) {
+    for i in (0..HIDDEN).step_by(16) {
+        unsafe {
+            let a = acc.as_mut_ptr().add(i) as *mut __m256i;
+            let w = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+            _mm256_storeu_si256(a, _mm256_sub_epi16(_mm256_loadu_si256(a), w));
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn screlu_dot_avx2(input: &[i16; HIDDEN], weights: &[i16], qa: i16) -> i32 {
+    let zero = _mm256_setzero_si256();
+    let max = _mm256_set1_epi16(qa);
+    let mut sum = _mm256_setzero_si256();
+
+    for i in (0..HIDDEN).step_by(16) {
+        unsafe {
+            let x = _mm256_loadu_si256(input.as_ptr().add(i) as *const __m256i);
+            let w = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+            let x = _mm256_min_epi16(_mm256_max_epi16(x, zero), max);
+
+            let x_lo = _mm256_cvtepi16_epi32(_mm256_castsi256_si128(x));
+            let x_hi = _mm256_cvtepi16_epi32(_mm256_extracti128_si256::<1>(x));
+            let w_lo = _mm256_cvtepi16_epi32(_mm256_castsi256_si128(w));
+            let w_hi = _mm256_cvtepi16_epi32(_mm256_extracti128_si256::<1>(w));
+
+            let lo = _mm256_mullo_epi32(_mm256_mullo_epi32(x_lo, x_lo), w_lo);
+            let hi = _mm256_mullo_epi32(_mm256_mullo_epi32(x_hi, x_hi), w_hi);
+            sum = _mm256_add_epi32(sum, _mm256_add_epi32(lo, hi));
+        }
+    }
+
+    let mut lanes = [0i32; 8];
+    unsafe { _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum) };
+    lanes
+        .iter()
+        .fold(0, |total, &lane| total.wrapping_add(lane))
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+unsafe fn add_sse41(acc: &mut [i16; HIDDEN], weights: &[i16]) {
+    for i in (0..HIDDEN).step_by(8) {
+        unsafe {
+            let a = acc.as_mut_ptr().add(i) as *mut __m128i;
+            let w = _mm_loadu_si128(weights.as_ptr().add(i) as *const __m128i);
+            _mm_storeu_si128(a, _mm_add_epi16(_mm_loadu_si128(a), w));
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+unsafe fn sub_sse41(acc: &mut [i16; HIDDEN], weights: &[i16]) {
+    for i in (0..HIDDEN).step_by(8) {
+        unsafe {
+            let a = acc.as_mut_ptr().add(i) as *mut __m128i;
+            let w = _mm_loadu_si128(weights.as_ptr().add(i) as *const __m128i);
+            _mm_storeu_si128(a, _mm_sub_epi16(_mm_loadu_si128(a), w));
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+unsafe fn screlu_dot_sse41(input: &[i16; HIDDEN], weights: &[i16], qa: i16) -> i32 {
+    let zero = _mm_setzero_si128();
+    let max = _mm_set1_epi16(qa);
+    let mut sum = _mm_setzero_si128();
+
+    for i in (0..HIDDEN).step_by(8) {
+        unsafe {
+            let x = _mm_loadu_si128(input.as_ptr().add(i) as *const __m128i);
+            let w = _mm_loadu_si128(weights.as_ptr().add(i) as *const __m128i);
+            let x = _mm_min_epi16(_mm_max_epi16(x, zero), max);
+
+            let x_lo = _mm_cvtepi16_epi32(x);
+            let x_hi = _mm_cvtepi16_epi32(_mm_srli_si128::<8>(x));
+            let w_lo = _mm_cvtepi16_epi32(w);
+            let w_hi = _mm_cvtepi16_epi32(_mm_srli_si128::<8>(w));
+
+            let lo = _mm_mullo_epi32(_mm_mullo_epi32(x_lo, x_lo), w_lo);
+            let hi = _mm_mullo_epi32(_mm_mullo_epi32(x_hi, x_hi), w_hi);
+            sum = _mm_add_epi32(sum, _mm_add_epi32(lo, hi));
+        }
+    }
+
+    let mut lanes = [0i32; 4];
+    unsafe { _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, sum) };
+    lanes
+        .iter()
+        .fold(0, |total, &lane| total.wrapping_add(lane))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Level, detect};
+    use crate::BitBoard;
+    use crate::board::Board;
+    use crate::defs::{NrOf, Sides};
+    use crate::evaluate::nnue::{Accumulator, HIDDEN, INPUTS, Network};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    const FENS: [&str; 10] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1",
+        "4k3/8/8/8/n7/8/P7/4K3 b - - 0 1",
+        "6k1/5ppp/8/8/8/8/5PPP/3R2K1 b - - 0 1",
+        "2kr3r/ppp2ppp/2n5/2b1p3/4P1b1/2NP1N2/PPP2PPP/R1B1KB1R b KQ - 3 9",
+    ];
+
+    fn available() -> Vec<Level> {
+        [Level::Scalar, Level::Sse41, Level::Avx2, Level::Avx512]
+            .into_iter()
+            .filter(|&level| level <= detect())
+            .collect()
+    }
+
+    // A random network with large weights, so that the accumulators go well
+    // outside of the SCReLU range on both sides, and the output layer sums
+    // overflow.
+    fn random_network() -> Network {
+        let mut rng = ChaChaRng::seed_from_u64(0x5EED);
+        let mut bytes = Vec::new();
+        let sizes = [
+            (INPUTS * HIDDEN, 300),
+            (HIDDEN, 300),
+            (2 * HIDDEN + 1, i16::MAX),
+        ];
+        for (n, range) in sizes {
+            for _ in 0..n {
+                let value: i16 = rng.random_range(-range..=range);
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        Network::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn simd_matches_scalar() {
+        let network = random_network();
+        let levels = available();
+
+        for fen in FENS {
+            let mut board = Board::new();
+            board.fen_read(Some(fen)).unwrap();
+            let side = board.game_state.side_to_move;
+
+            let scalar = Accumulator::refresh(&network, &board, Level::Scalar);
+            let expected = network.output(
+                &scalar.values[side],
+                &scalar.values[side ^ 1],
+                Level::Scalar,
+            );
+
+            for &level in &levels {
+                let mut acc = Accumulator::refresh(&network, &board, level);
+                assert_eq!(acc.values, scalar.values, "{level:?} accumulator, {fen}");
+
+                let output = network.output(&acc.values[side], &acc.values[side ^ 1], level);
+                assert_eq!(output, expected, "{level:?} output, {fen}");
+
+                // Removing every piece leaves the biases.
+                for colour in [Sides::WHITE, Sides::BLACK] {
+                    for piece in 0..NrOf::PIECE_TYPES {
+                        let mut bb = board.pieces[colour][piece];
+                        while bb.0 > 0 {
+                            let square = BitBoard::next(&mut bb).0;
+                            acc.remove(&network, colour, piece, square, level);
+                        }
+                    }
+                }
+                for values in acc.values {
+                    assert_eq!(
+                        &values[..],
+                        &network.feature_bias[..],
+                        "{level:?} remove, {fen}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::BitBoard;
+use crate::board::Board;
+use crate::defs::{NrOf, Pieces, Side, Sides};
+
+// Pawn structure evaluation. Doubled, isolated and backward pawns are
+// penalized; connected and passed pawns get a bonus that grows as they
+// advance. Because the pawn structure rarely changes, its score is cached in
+// a pawn hash table, indexed by a Zobrist key of the pawns only. Terms that
+// also depend on the other pieces (a free path to promotion, the distance of
+// the kings to a passed pawn) are computed afterwards, from the passed pawns
+// stored in the table.
+
+// Bonuses and penalties as (middlegame, endgame), indexed by the rank of the
+// pawn as seen from its own side.
+pub const DOUBLED: (i32, i32) = (-10, -20);
+pub const ISOLATED: (i32, i32) = (-10, -15);
+pub const BACKWARD: (i32, i32) = (-8, -10);
+pub const CONNECTED: [i32; NrOf::RANKS] = [0, 5, 8, 12, 20, 35, 60, 0];
+pub const PASSED_MG: [i32; NrOf::RANKS] = [0, 5, 10, 15, 30, 50, 80, 0];
+pub const PASSED_EG: [i32; NrOf::RANKS] = [0, 10, 15, 25, 45, 75, 120, 0];
+
+// Endgame only: a passed pawn with no pieces in front of it, and a passed
+// pawn the enemy king is far away from (and our own king is close to).
+pub const FREE_PATH: [i32; NrOf::RANKS] = [0, 0, 5, 10, 20, 35, 60, 0];
+pub const KING_PROXIMITY: [i32; NrOf::RANKS] = [0, 0, 0, 1, 2, 3, 4, 0];
+const THEIR_KING_DISTANCE: i32 = 4;
+const OUR_KING_DISTANCE: i32 = 2;
+
+const PAWN_HASH_ENTRIES: usize = 1 << 14;
+
+#[derive(Copy, Clone)]
+pub struct PawnEntry {
+    key: u64,
+    mg: i32,
+    eg: i32,
+    passed: [BitBoard; Sides::BOTH],
+}
+
+impl PawnEntry {
+    fn new() -> Self {
+        Self {
+            key: 0,
+            mg: 0,
+            eg: 0,
+            passed: [BitBoard::EMPTY; Sides::BOTH],
+        }
+    }
+}
+
+// An always-replace table: the pawn structure in a search changes slowly,
+// so the most recent entries are the most useful.
+pub struct PawnHashTable {
+    entries: Vec<PawnEntry>,
+}
+
+impl PawnHashTable {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![PawnEntry::new(); PAWN_HASH_ENTRIES],
+        }
+    }
+
+    fn probe(&self, key: u64) -> Option<PawnEntry> {
+        let entry = self.entries[key as usize & (PAWN_HASH_ENTRIES - 1)];
+        if entry.key == key { Some(entry) } else { None }
+    }
+
+    fn store(&mut self, entry: PawnEntry) {
+        self.entries[entry.key as usize & (PAWN_HASH_ENTRIES - 1)] = entry;
+    }
+}
+
+// The (middlegame, endgame) score of the pawn structure, from white's point
+// of view.
+pub fn evaluate_pawns(board: &Board, table: &mut PawnHashTable) -> (i32, i32) {
+    let key = board.game_state.pawn_key;
+    let entry = match table.probe(key) {
+        Some(entry) => entry,
+        None => {
+            let entry = pawn_structure(board, key);
+            table.store(entry);
+            entry
+        }
+    };
+
+    let white = passed_pawn_extras(board, Sides::WHITE, entry.passed[Sides::WHITE]);
+    let black = passed_pawn_extras(board, Sides::BLACK, entry.passed[Sides::BLACK]);
+    (entry.mg, entry.eg + white - black)
+}
+
+// Evaluate everything that only depends on the pawns.
+fn pawn_structure(board: &Board, key: u64) -> PawnEntry {
+    let mut entry = PawnEntry::new();
+    entry.key = key;
+
+    for side in [Sides::WHITE, Sides::BLACK] {
+        let sign = if side == Sides::WHITE { 1 } else { -1 };
+        classify_pawns(board, side, |square, pawn| {
+            let (mut mg, mut eg) = (0, 0);
+            if pawn.doubled {
+                mg += DOUBLED.0;
+                eg += DOUBLED.1;
+            }
+            if pawn.isolated {
+                mg += ISOLATED.0;
+                eg += ISOLATED.1;
+            }
+            if pawn.backward {
+                mg += BACKWARD.0;
+                eg += BACKWARD.1;
+            }
+            if pawn.connected {
+                mg += CONNECTED[pawn.relative_rank];
+                eg += CONNECTED[pawn.relative_rank];
+            }
+            if pawn.passed {
+                mg += PASSED_MG[pawn.relative_rank];
+                eg += PASSED_EG[pawn.relative_rank];
+                entry.passed[side].0 |= 1 << square;
+            }
+
+            entry.mg += sign * mg;
+            entry.eg += sign * eg;
+        });
+    }
+
+    entry
+}
+
+// What the pawn structure evaluation knows about a single pawn.
+pub struct PawnFeatures {
+    pub relative_rank: usize,
+    pub doubled: bool,
+    pub isolated: bool,
+    pub backward: bool,
+    pub connected: bool,
+    pub passed: bool,
+}
+
+// Call "f" with the square and the features of each pawn of one side.
+pub fn classify_pawns(board: &Board, side: Side, mut f: impl FnMut(usize, PawnFeatures)) {
+    let own = board.pieces[side][Pieces::PAWN].0;
+    let theirs = board.pieces[side ^ 1][Pieces::PAWN].0;
+    let their_attacks = pawn_attacks(side ^ 1, theirs);
+
+    let mut pawns = board.pieces[side][Pieces::PAWN];
+    while pawns.0 > 0 {
+        let square = BitBoard::next(&mut pawns).0;
+        let file = square % 8;
+        let rank = square / 8;
+        let adjacent = adjacent_files(file);
+        let forward = forward_ranks(side, rank);
+
+        // Only the rearmost pawn of a doubled pair is penalized (and can't
+        // be passed: the pawn in front of it blocks).
+        let doubled = own & forward & file_mask(file) != 0;
+        let isolated = own & adjacent == 0;
+        let passed = !doubled && theirs & forward & (file_mask(file) | adjacent) == 0;
+
+        let phalanx = own & adjacent & (0xFF << (rank * 8)) != 0;
+        let supported = own & pawn_attacks(side ^ 1, 1 << square) != 0;
+        let connected = phalanx || supported;
+
+        // A backward pawn can't be supported by a neighbour anymore, and its
+        // advance is stopped by an enemy pawn.
+        let backward = !isolated
+            && !connected
+            && own & adjacent & !forward == 0
+            && their_attacks & (1 << stop_square(side, square)) != 0;
+
+        f(
+            square,
+            PawnFeatures {
+                relative_rank: relative_rank(side, rank),
+                doubled,
+                isolated,
+                backward,
+                connected,
+                passed,
+            },
+        );
+    }
+}
+
+// Endgame terms for the passed pawns of one side.
+fn passed_pawn_extras(board: &Board, side: Side, mut passed: BitBoard) -> i32 {
+    let mut score = 0;
+
+    while passed.0 > 0 {
+        let square = BitBoard::next(&mut passed).0;
+        let pawn = classify_passed_pawn(board, side, square);
+        if pawn.free_path {
+            score += FREE_PATH[pawn.relative_rank];
+        }
+        score += KING_PROXIMITY[pawn.relative_rank] * pawn.king_distance;
+    }
+
+    score
+}
+
+// What the evaluation knows about a passed pawn, besides the pawn
+// structure.
+pub struct PassedPawnFeatures {
+    pub relative_rank: usize,
+    // No pieces in front of the pawn.
+    pub free_path: bool,
+    // The weighted distance of the enemy king to the stop square, minus
+    // that of our own king.
+    pub king_distance: i32,
+}
+
+pub fn classify_passed_pawn(board: &Board, side: Side, square: usize) -> PassedPawnFeatures {
+    let occupancy = board.occupancy(Sides::BOTH).0;
+    let our_king = board.king_square(side).0;
+    let their_king = board.king_square(side ^ 1).0;
+    let rank = square / 8;
+    let front_span = forward_ranks(side, rank) & file_mask(square % 8);
+    let stop_square = stop_square(side, square);
+
+    PassedPawnFeatures {
+        relative_rank: relative_rank(side, rank),
+        free_path: occupancy & front_span == 0,
+        king_distance: THEIR_KING_DISTANCE * distance(their_king, stop_square)
+            - OUR_KING_DISTANCE * distance(our_king, stop_square),
+    }
+}
+
+// The square in front of a pawn.
+fn stop_square(side: Side, square: usize) -> usize {
+    if side == Sides::WHITE {
+        square + 8
+    } else {
+        square - 8
+    }
+}
+
+fn relative_rank(side: Side, rank: usize) -> usize {
+    if side == Sides::WHITE { rank } else { 7 - rank }
+}
+
+fn file_mask(file: usize) -> u64 {
+    BitBoard::A_FILE.0 << file
+}
+
+fn adjacent_files(file: usize) -> u64 {
+    let mut mask = 0;
+    if file > 0 {
+        mask |= file_mask(file - 1);
+    }
+    if file < 7 {
+        mask |= file_mask(file + 1);
+    }
+    mask
+}
+
+// All ranks in front of the given rank, as seen from the side's point of
+// view.
+fn forward_ranks(side: Side, rank: usize) -> u64 {
+    if side == Sides::WHITE {
+        if rank == 7 { 0 } else { !0 << ((rank + 1) * 8) }
+    } else {
+        (1 << (rank * 8)) - 1
+    }
+}
+
+// The squares attacked by the given pawns.
+pub fn pawn_attacks(side: Side, pawns: u64) -> u64 {
+    if side == Sides::WHITE {
+        ((pawns & !BitBoard::H_FILE.0) << 9) | ((pawns & !BitBoard::A_FILE.0) << 7)
+    } else {
+        ((pawns & !BitBoard::A_FILE.0) >> 9) | ((pawns & !BitBoard::H_FILE.0) >> 7)
+    }
+}
+
+// The number of king moves between two squares.
+fn distance(a: usize, b: usize) -> i32 {
+    let files = (a % 8).abs_diff(b % 8);
+    let ranks = (a / 8).abs_diff(b / 8);
+    files.max(ranks) as i32
+}
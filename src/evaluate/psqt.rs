@@ -0,0 +1,248 @@
+use crate::BitBoard;
+use crate::board::Board;
+use crate::defs::{NrOf, Pieces, Side, Sides};
+
+// Piece-square tables. Each piece gets a bonus or penalty depending on the
+// square it stands on, with separate tables for the middlegame and the
+// endgame. The material value of the piece is included in the tables.
+//
+// The tables are written from white's point of view, as the board looks
+// from white's side: the first row is the 8th rank. A white piece's square
+// is flipped to find its entry; for black, the square itself is used.
+
+pub type Psqt = [i32; NrOf::SQUARES];
+
+// Material values in the middlegame and endgame, indexed by piece type.
+pub const VALUES_MG: [i32; NrOf::PIECE_TYPES] = [0, 1025, 477, 365, 337, 82];
+pub const VALUES_EG: [i32; NrOf::PIECE_TYPES] = [0, 936, 512, 297, 281, 94];
+
+// The game phase is counted from the non-pawn material on the board. It is
+// MAX_PHASE in the opening and goes down to 0 when only kings and pawns are
+// left. Promotions can push it above MAX_PHASE; it is capped when used.
+pub const PHASE_VALUES: [i32; NrOf::PIECE_TYPES + 1] = [0, 4, 2, 1, 1, 0, 0];
+pub const MAX_PHASE: i32 = 24;
+
+#[rustfmt::skip]
+pub const FLIP: [usize; NrOf::SQUARES] = [
+    56, 57, 58, 59, 60, 61, 62, 63,
+    48, 49, 50, 51, 52, 53, 54, 55,
+    40, 41, 42, 43, 44, 45, 46, 47,
+    32, 33, 34, 35, 36, 37, 38, 39,
+    24, 25, 26, 27, 28, 29, 30, 31,
+    16, 17, 18, 19, 20, 21, 22, 23,
+     8,  9, 10, 11, 12, 13, 14, 15,
+     0,  1,  2,  3,  4,  5,  6,  7,
+];
+
+#[rustfmt::skip]
+pub const KING_MG: Psqt = [
+    -65,  23,  16, -15, -56, -34,   2,  13,
+     29,  -1, -20,  -7,  -8,  -4, -38, -29,
+     -9,  24,   2, -16, -20,   6,  22, -22,
+    -17, -20, -12, -27, -30, -25, -14, -36,
+    -49,  -1, -27, -39, -46, -44, -33, -51,
+    -14, -14, -22, -46, -44, -30, -15, -27,
+      1,   7,  -8, -64, -43, -16,   9,   8,
+    -15,  36,  12, -54,   8, -28,  24,  14,
+];
+
+#[rustfmt::skip]
+pub const KING_EG: Psqt = [
+    -74, -35, -18, -18, -11,  15,   4, -17,
+    -12,  17,  14,  17,  17,  38,  23,  11,
+     10,  17,  23,  15,  20,  45,  44,  13,
+     -8,  22,  24,  27,  26,  33,  26,   3,
+    -18,  -4,  21,  24,  27,  23,   9, -11,
+    -19,  -3,  11,  21,  23,  16,   7,  -9,
+    -27, -11,   4,  13,  14,   4,  -5, -17,
+    -53, -34, -21, -11, -28, -14, -24, -43,
+];
+
+#[rustfmt::skip]
+pub const QUEEN_MG: Psqt = [
+    -28,   0,  29,  12,  59,  44,  43,  45,
+    -24, -39,  -5,   1, -16,  57,  28,  54,
+    -13, -17,   7,   8,  29,  56,  47,  57,
+    -27, -27, -16, -16,  -1,  17,  -2,   1,
+     -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+    -14,   2, -11,  -2,  -5,   2,  14,   5,
+    -35,  -8,  11,   2,   8,  15,  -3,   1,
+     -1, -18,  -9,  10, -15, -25, -31, -50,
+];
+
+#[rustfmt::skip]
+pub const QUEEN_EG: Psqt = [
+     -9,  22,  22,  27,  27,  19,  10,  20,
+    -17,  20,  32,  41,  58,  25,  30,   0,
+    -20,   6,   9,  49,  47,  35,  19,   9,
+      3,  22,  24,  45,  57,  40,  57,  36,
+    -18,  28,  19,  47,  31,  34,  39,  23,
+    -16, -27,  15,   6,   9,  17,  10,   5,
+    -22, -23, -30, -16, -16, -23, -36, -32,
+    -33, -28, -22, -43,  -5, -32, -20, -41,
+];
+
+#[rustfmt::skip]
+pub const ROOK_MG: Psqt = [
+     32,  42,  32,  51,  63,   9,  31,  43,
+     27,  32,  58,  62,  80,  67,  26,  44,
+     -5,  19,  26,  36,  17,  45,  61,  16,
+    -24, -11,   7,  26,  24,  35,  -8, -20,
+    -36, -26, -12,  -1,   9,  -7,   6, -23,
+    -45, -25, -16, -17,   3,   0,  -5, -33,
+    -44, -16, -20,  -9,  -1,  11,  -6, -71,
+    -19, -13,   1,  17,  16,   7, -37, -26,
+];
+
+#[rustfmt::skip]
+pub const ROOK_EG: Psqt = [
+     13,  10,  18,  15,  12,  12,   8,   5,
+     11,  13,  13,  11,  -3,   3,   8,   3,
+      7,   7,   7,   5,   4,  -3,  -5,  -3,
+      4,   3,  13,   1,   2,   1,  -1,   2,
+      3,   5,   8,   4,  -5,  -6,  -8, -11,
+     -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+     -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+     -9,   2,   3,  -1,  -5, -13,   4, -20,
+];
+
+#[rustfmt::skip]
+pub const BISHOP_MG: Psqt = [
+    -29,   4, -82, -37, -25, -42,   7,  -8,
+    -26,  16, -18, -13,  30,  59,  18, -47,
+    -16,  37,  43,  40,  35,  50,  37,  -2,
+     -4,   5,  19,  50,  37,  37,   7,  -2,
+     -6,  13,  13,  26,  34,  12,  10,   4,
+      0,  15,  15,  15,  14,  27,  18,  10,
+      4,  15,  16,   0,   7,  21,  33,   1,
+    -33,  -3, -14, -21, -13, -12, -39, -21,
+];
+
+#[rustfmt::skip]
+pub const BISHOP_EG: Psqt = [
+    -14, -21, -11,  -8,  -7,  -9, -17, -24,
+     -8,  -4,   7, -12,  -3, -13,  -4, -14,
+      2,  -8,   0,  -1,  -2,   6,   0,   4,
+     -3,   9,  12,   9,  14,  10,   3,   2,
+     -6,   3,  13,  19,   7,  10,  -3,  -9,
+    -12,  -3,   8,  10,  13,   3,  -7, -15,
+    -14, -18,  -7,  -1,   4,  -9, -15, -27,
+    -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+];
+
+#[rustfmt::skip]
+pub const KNIGHT_MG: Psqt = [
+   -167, -89, -34, -49,  61, -97, -15,-107,
+    -73, -41,  72,  36,  23,  62,   7, -17,
+    -47,  60,  37,  65,  84, 129,  73,  44,
+     -9,  17,  19,  53,  37,  69,  18,  22,
+    -13,   4,  16,  13,  28,  19,  21,  -8,
+    -23,  -9,  12,  10,  19,  17,  25, -16,
+    -29, -53, -12,  -3,  -1,  18, -14, -19,
+   -105, -21, -58, -33, -17, -28, -19, -23,
+];
+
+#[rustfmt::skip]
+pub const KNIGHT_EG: Psqt = [
+    -58, -38, -13, -28, -31, -27, -63, -99,
+    -25,  -8, -25,  -2,  -9, -25, -24, -52,
+    -24, -20,  10,   9,  -1,  -9, -19, -41,
+    -17,   3,  22,  22,  22,  11,   8, -18,
+    -18,  -6,  16,  25,  16,  17,   4, -18,
+    -23,  -3,  -1,  15,  10,  -3, -20, -22,
+    -42, -20, -10,  -5,  -2, -20, -23, -44,
+    -29, -51, -23, -15, -22, -18, -50, -64,
+];
+
+#[rustfmt::skip]
+pub const PAWN_MG: Psqt = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     98, 134,  61,  95,  68, 126,  34, -11,
+     -6,   7,  26,  31,  65,  56,  25, -20,
+    -14,  13,   6,  21,  23,  12,  17, -23,
+    -27,  -2,  -5,  12,  17,   6,  10, -25,
+    -26,  -4,  -4, -10,   3,   3,  33, -12,
+    -35,  -1, -20, -23, -15,  24,  38, -22,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+pub const PAWN_EG: Psqt = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+    178, 173, 158, 134, 147, 132, 165, 187,
+     94, 100,  85,  67,  56,  53,  82,  84,
+     32,  24,  13,   5,  -2,   4,  17,  17,
+     13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+      4,   7,  -6,   1,   0,  -5,  -1,  -8,
+     13,   8,   8,  10,  13,   0,   2,  -7,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+pub const PSQT_MG: [Psqt; NrOf::PIECE_TYPES] = with_values(
+    [KING_MG, QUEEN_MG, ROOK_MG, BISHOP_MG, KNIGHT_MG, PAWN_MG],
+    VALUES_MG,
+);
+
+pub const PSQT_EG: [Psqt; NrOf::PIECE_TYPES] = with_values(
+    [KING_EG, QUEEN_EG, ROOK_EG, BISHOP_EG, KNIGHT_EG, PAWN_EG],
+    VALUES_EG,
+);
+
+// Add the material value of each piece to all squares of its table.
+const fn with_values(
+    mut tables: [Psqt; NrOf::PIECE_TYPES],
+    values: [i32; NrOf::PIECE_TYPES],
+) -> [Psqt; NrOf::PIECE_TYPES] {
+    let mut piece = 0;
+    while piece < NrOf::PIECE_TYPES {
+        let mut square = 0;
+        while square < NrOf::SQUARES {
+            tables[piece][square] += values[piece];
+            square += 1;
+        }
+        piece += 1;
+    }
+    tables
+}
+
+// The table entry for a piece of the given side on the given square.
+pub fn index(side: Side, square: usize) -> usize {
+    if side == Sides::WHITE {
+        FLIP[square]
+    } else {
+        square
+    }
+}
+
+// Compute the middlegame and endgame PSQT scores of both sides from
+// scratch. During the game, they are updated incrementally.
+pub fn apply(board: &Board) -> ([i32; Sides::BOTH], [i32; Sides::BOTH]) {
+    let mut mg = [0; Sides::BOTH];
+    let mut eg = [0; Sides::BOTH];
+
+    for side in [Sides::WHITE, Sides::BLACK] {
+        for piece in 0..NrOf::PIECE_TYPES {
+            let mut bb = board.pieces[side][piece];
+            while bb.0 > 0 {
+                let square = BitBoard::next(&mut bb);
+                let s = index(side, square.0);
+                mg[side] += PSQT_MG[piece][s];
+                eg[side] += PSQT_EG[piece][s];
+            }
+        }
+    }
+
+    (mg, eg)
+}
+
+// Compute the game phase from scratch.
+pub fn game_phase(board: &Board) -> i32 {
+    [Pieces::QUEEN, Pieces::ROOK, Pieces::BISHOP, Pieces::KNIGHT]
+        .iter()
+        .map(|&piece| {
+            let count = board.pieces[Sides::WHITE][piece].pop_count()
+                + board.pieces[Sides::BLACK][piece].pop_count();
+            PHASE_VALUES[piece] * count as i32
+        })
+        .sum()
+}
@@ -1,11 +1,13 @@
 mod bitboard;
 mod board;
+mod datagen;
 mod defs;
 mod evaluate;
 mod movegen;
 mod movelist;
 mod perft;
 mod search;
+mod tune;
 mod uci;
 
 use crate::bitboard::*;
@@ -14,6 +16,13 @@ use crate::movelist::*;
 use board::*;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("tune") => return tune::run(&args[2..]),
+        Some("datagen") => return datagen::run(&args[2..]),
+        _ => (),
+    }
+
     let mut board = Board::new();
     let init_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
     let test_fen = "4k3/8/8/8/n7/8/P7/4K3 w - - 0 1";
@@ -1,4 +1,4 @@
-use crate::defs::{PIECE_CHAR_SMALL, Piece, SQUARE_NAME, Square};
+use crate::defs::{PIECE_CHAR_SMALL, Piece, Pieces, SQUARE_NAME, Square};
 
 /* "Shift" is an enum which contains the number of bits that needed to be shifted to store
  * move data in a specific place within the u64 integer. This makes sure that, should the
@@ -58,6 +58,34 @@ impl Move {
     pub fn castling(&self) -> bool {
         ((self.data >> Shift::CASTLING as u64) & 0x1) as u8 == 1
     }
+    // The sort score is used by the search to decide in which order moves
+    // are tried. It is stored in the upper bits of the move data.
+    pub fn get_sort_score(&self) -> u32 {
+        ((self.data >> Shift::SORTSCORE as u64) & 0xFFFF_FFFF) as u32
+    }
+
+    pub fn set_sort_score(&mut self, value: u32) {
+        let mask: usize = 0xFFFF_FFFF << Shift::SORTSCORE;
+        self.data = (self.data & !mask) | ((value as usize) << Shift::SORTSCORE);
+    }
+
+    // Is this a capture (including en passant)?
+    pub fn is_capture(&self) -> bool {
+        self.captured() != Pieces::NONE || self.en_passant()
+    }
+
+    // A quiet move is neither a capture nor a promotion.
+    pub fn is_quiet(&self) -> bool {
+        !self.is_capture() && self.promoted() == Pieces::NONE
+    }
+
+    // The move data without the sort score. This is what gets stored in the
+    // transposition table, and what should be compared to see if two moves
+    // are the same.
+    pub fn get_move(&self) -> u32 {
+        (self.data & 0x00FF_FFFF) as u32
+    }
+
     #[inline]
     pub const fn null() -> Self {
         Self { data: 0 }
@@ -3,22 +3,17 @@ use crate::{
     defs::{Pieces, Square},
 };
 
-use super::MoveGenerator;
+use super::{MoveGenerator, MoveType};
 
 impl MoveGenerator {
-    pub fn generate_king_moves(&self, board: &Board, list: &mut MoveList) {
+    pub fn generate_king_moves(&self, board: &Board, list: &mut MoveList, move_type: MoveType) {
+        let targets = Self::target_squares(board, move_type);
         let side = board.game_state.side_to_move as usize;
         let mut kings = board.pieces[side][Pieces::KING];
         //println!("{}", occupancy);
         while kings.0 > 0 {
             let from = BitBoard::next(&mut kings);
-            let mut to_bb = self.king_attacks[from.0];
-            // if white
-            if side == 0 {
-                to_bb &= !board.white_occupied()
-            } else {
-                to_bb &= !board.black_occupied()
-            }
+            let mut to_bb = self.king_attacks[from.0] & targets;
             //println!("{}", to_bb);
             while to_bb.0 > 0 {
                 let to = BitBoard::next(&mut to_bb);
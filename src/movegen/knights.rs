@@ -3,10 +3,11 @@ use crate::{
     defs::{Pieces, Square},
 };
 
-use super::MoveGenerator;
+use super::{MoveGenerator, MoveType};
 
 impl MoveGenerator {
-    pub fn generate_knight_moves(&self, board: &Board, list: &mut MoveList) {
+    pub fn generate_knight_moves(&self, board: &Board, list: &mut MoveList, move_type: MoveType) {
+        let targets = Self::target_squares(board, move_type);
         let side = board.game_state.side_to_move as usize;
         let mut knights = board.pieces[side][Pieces::KNIGHT];
         //println!("Initial bitboard {:?}", bb);
@@ -14,13 +15,7 @@ impl MoveGenerator {
             let from = BitBoard::next(&mut knights);
             //println!("From {}", from);
             //println!("From bb {:?}", from_bb);
-            let mut to_bb: BitBoard = self.knight_moves_array[from.0];
-            // if white
-            if side == 0 {
-                to_bb &= !board.white_occupied()
-            } else {
-                to_bb &= !board.black_occupied()
-            }
+            let mut to_bb: BitBoard = self.knight_moves_array[from.0] & targets;
             //println!("To bb {:?}", to_bb);
             while to_bb.0 > 0 {
                 let to = BitBoard::next(&mut to_bb);
@@ -5,6 +5,7 @@ mod king;
 mod knights;
 mod magics;
 mod pawns;
+mod see;
 mod slide;
 pub mod uci;
 
@@ -21,6 +22,15 @@ use crate::defs::Sides;
 use crate::defs::Square;
 use crate::movelist::*;
 
+// Generate either all pseudo-legal moves, or only the captures. Capture
+// generation also includes pawn pushes to the promotion rank, so the
+// quiescence search sees promotions.
+#[derive(Copy, Clone, PartialEq)]
+pub enum MoveType {
+    All,
+    Captures,
+}
+
 // This is a list of all pieces a pawn can promote to.
 const PROMOTION_PIECES: [usize; 4] = [Pieces::QUEEN, Pieces::ROOK, Pieces::BISHOP, Pieces::KNIGHT];
 
@@ -76,32 +86,42 @@ impl MoveGenerator {
     }
 
     pub fn generate_all_moves(&self, board: &Board, list: &mut MoveList) {
+        self.generate_moves(board, list, MoveType::All);
+    }
+
+    pub fn generate_captures(&self, board: &Board, list: &mut MoveList) {
+        self.generate_moves(board, list, MoveType::Captures);
+    }
+
+    fn generate_moves(&self, board: &Board, list: &mut MoveList, move_type: MoveType) {
         //let initial_count = list.len();
-        Self::generate_pawn_moves(&self, board, list);
+        Self::generate_pawn_moves(&self, board, list, move_type);
         //println!("Pawn moves: {}", list.len() - initial_count);
 
         //let count_before = list.len();
-        Self::generate_knight_moves(&self, board, list);
+        Self::generate_knight_moves(&self, board, list, move_type);
         //println!("Knight moves: {}", list.len() - count_before);
 
         //let count_before = list.len();
-        Self::generate_rook_moves(&self, board, list);
+        Self::generate_rook_moves(&self, board, list, move_type);
         //println!("Rook moves: {}", list.len() - count_before);
 
         //let count_before = list.len();
-        Self::generate_bishop_moves(&self, board, list);
+        Self::generate_bishop_moves(&self, board, list, move_type);
         //println!("Bishop moves: {}", list.len() - count_before);
 
         //let count_before = list.len();
-        Self::generate_queen_moves(&self, board, list);
+        Self::generate_queen_moves(&self, board, list, move_type);
         //println!("Queen moves: {}", list.len() - count_before);
 
         //let count_before = list.len();
-        Self::generate_king_moves(&self, board, list);
+        Self::generate_king_moves(&self, board, list, move_type);
         //println!("King moves: {}", list.len() - count_before);
 
         //let count_before = list.len();
-        Self::castling(&self, board, list);
+        if move_type == MoveType::All {
+            Self::castling(&self, board, list);
+        }
         //println!("Castling moves: {}", list.len() - count_before);
     }
 
@@ -129,6 +149,16 @@ impl MoveGenerator {
             || ((bb_pawns & board.pieces[attacker][Pieces::PAWN]).0 > 0)
     }
 
+    // The squares a piece of the side to move may go to: every square not
+    // occupied by its own pieces, or only the opponent's pieces when
+    // generating captures.
+    fn target_squares(board: &Board, move_type: MoveType) -> BitBoard {
+        match move_type {
+            MoveType::All => !board.occupancy(board.game_state.side_to_move),
+            MoveType::Captures => board.occupancy(board.opponent()),
+        }
+    }
+
     pub fn add_move(
         &self,
         board: &Board,
@@ -1,4 +1,4 @@
-use super::MoveGenerator;
+use super::{MoveGenerator, MoveType};
 
 use crate::{
     Board, MoveList,
@@ -25,8 +25,14 @@ impl MoveGenerator {
         single_pushes.south_one() & empty & BitBoard::RANK5
     }
 
-    pub fn generate_pawn_pushes(&self, board: &Board, list: &mut MoveList) {
+    pub fn generate_pawn_pushes(&self, board: &Board, list: &mut MoveList, move_type: MoveType) {
         let side = board.game_state.side_to_move;
+        // When generating captures, only pushes that promote are included.
+        let targets = match (move_type, side) {
+            (MoveType::All, _) => !BitBoard::EMPTY,
+            (MoveType::Captures, Sides::WHITE) => !BitBoard::NOT_RANK_8,
+            (MoveType::Captures, _) => !BitBoard::NOT_RANK_1,
+        };
         let mut pawns = board.pieces[side][Pieces::PAWN];
         //println!("Initial pawns bitboard {:?}", w_pawns);
         let empty_bb: BitBoard = !board.occupancy(Sides::BOTH);
@@ -44,6 +50,7 @@ impl MoveGenerator {
                 }
                 _ => panic!(),
             };
+            to_bb &= targets;
             while to_bb.0 > 0 {
                 let to = BitBoard::next(&mut to_bb);
                 //println!("Adding pawn move from {} to {}", from, to);
@@ -72,8 +79,8 @@ impl MoveGenerator {
         }
     }
 
-    pub fn generate_pawn_moves(&self, board: &Board, list: &mut MoveList) {
-        self.generate_pawn_pushes(board, list);
+    pub fn generate_pawn_moves(&self, board: &Board, list: &mut MoveList, move_type: MoveType) {
+        self.generate_pawn_pushes(board, list, move_type);
         self.generate_pawn_attacks(board, list);
     }
 
@@ -0,0 +1,270 @@
+use super::MoveGenerator;
+use super::bit_move::Move;
+use crate::{
+    BitBoard, Board,
+    defs::{NrOf, Pieces, Side, Sides, Square},
+};
+
+// Static Exchange Evaluation. SEE determines the material outcome of a
+// sequence of captures on one square, where both sides always recapture with
+// their least valuable piece and may stop capturing when that is better for
+// them. Sliders behind a capturing piece (x-rays) join the exchange when the
+// piece in front of them leaves. Pins are not taken into account.
+
+// Piece values used by SEE, indexed by piece type. The king is worth more
+// than everything else together, so capturing with it is only good when
+// the opponent has no attackers left.
+const SEE_VALUES: [i32; NrOf::PIECE_TYPES + 1] = [20000, 900, 500, 300, 300, 100, 0];
+
+// The pieces in order of increasing value, for finding the least valuable
+// attacker.
+const LEAST_VALUABLE_FIRST: [usize; NrOf::PIECE_TYPES] = [
+    Pieces::PAWN,
+    Pieces::KNIGHT,
+    Pieces::BISHOP,
+    Pieces::ROOK,
+    Pieces::QUEEN,
+    Pieces::KING,
+];
+
+impl MoveGenerator {
+    // All pieces of both sides attacking the given square, as if the board
+    // had the given occupancy. Pieces not in the occupancy are ignored.
+    pub fn attackers_to(&self, board: &Board, square: &Square, occupancy: BitBoard) -> BitBoard {
+        let w = &board.pieces[Sides::WHITE];
+        let b = &board.pieces[Sides::BLACK];
+        let rooks = w[Pieces::ROOK] | w[Pieces::QUEEN] | b[Pieces::ROOK] | b[Pieces::QUEEN];
+        let bishops = w[Pieces::BISHOP] | w[Pieces::QUEEN] | b[Pieces::BISHOP] | b[Pieces::QUEEN];
+
+        let attackers = (self.get_pawn_attacks_from_square(Sides::BLACK, square) & w[Pieces::PAWN])
+            | (self.get_pawn_attacks_from_square(Sides::WHITE, square) & b[Pieces::PAWN])
+            | (self.get_knight_attacks(square) & (w[Pieces::KNIGHT] | b[Pieces::KNIGHT]))
+            | (self.get_king_attacks(square) & (w[Pieces::KING] | b[Pieces::KING]))
+            | (self.get_slider_attacks(Pieces::ROOK, square, occupancy) & rooks)
+            | (self.get_slider_attacks(Pieces::BISHOP, square, occupancy) & bishops);
+
+        attackers & occupancy
+    }
+
+    // The material balance of the exchange started by the move, from the
+    // point of view of the side making it.
+    pub fn see(&self, board: &Board, m: Move) -> i32 {
+        if m.castling() {
+            return 0;
+        }
+
+        let to = m.to();
+        let (mut occupancy, first_victim) = Self::see_start(board, &m);
+        let mut attackers = self.attackers_to(board, &to, occupancy);
+        let mut side = board.game_state.side_to_move;
+
+        // gain[d] is the balance for the side capturing at depth d, if the
+        // exchange stopped after that capture.
+        let mut gain = [0i32; 32];
+        let mut d = 0;
+        gain[0] = first_victim;
+        let mut on_square = Self::see_piece(&m);
+
+        loop {
+            side ^= 1;
+            let Some((piece, square)) = Self::least_valuable_attacker(board, attackers, side)
+            else {
+                break;
+            };
+
+            d += 1;
+            gain[d] = SEE_VALUES[on_square] - gain[d - 1];
+            if d == gain.len() - 1 {
+                break;
+            }
+
+            occupancy ^= square.to_bb();
+            attackers |= self.xray_attackers(board, &to, occupancy);
+            attackers &= occupancy;
+            on_square = piece;
+        }
+
+        // Each side may decide not to capture; go back through the sequence
+        // to find the outcome with best play.
+        while d > 0 {
+            gain[d - 1] = -(-gain[d - 1]).max(gain[d]);
+            d -= 1;
+        }
+
+        gain[0]
+    }
+
+    // Does the move win at least "threshold" centipawns? This is faster than
+    // computing the exact SEE value, because it stops as soon as the outcome
+    // relative to the threshold is known.
+    pub fn see_ge(&self, board: &Board, m: Move, threshold: i32) -> bool {
+        if m.castling() {
+            return 0 >= threshold;
+        }
+
+        let to = m.to();
+        let (mut occupancy, first_victim) = Self::see_start(board, &m);
+
+        // Even if the opponent doesn't recapture, the threshold isn't met.
+        let mut swap = first_victim - threshold;
+        if swap < 0 {
+            return false;
+        }
+
+        // Even if the opponent recaptures the moved piece, it is met.
+        swap = SEE_VALUES[Self::see_piece(&m)] - swap;
+        if swap <= 0 {
+            return true;
+        }
+
+        let mut attackers = self.attackers_to(board, &to, occupancy);
+        let mut side = board.game_state.side_to_move;
+        let mut result = true;
+
+        loop {
+            side ^= 1;
+            let Some((piece, square)) = Self::least_valuable_attacker(board, attackers, side)
+            else {
+                break;
+            };
+
+            // A king can't capture into a defended square.
+            if piece == Pieces::KING {
+                let defenders = attackers & board.occupancy(side ^ 1);
+                return if defenders != BitBoard::EMPTY {
+                    result
+                } else {
+                    !result
+                };
+            }
+
+            result = !result;
+            swap = SEE_VALUES[piece] - swap;
+            if swap < result as i32 {
+                break;
+            }
+
+            occupancy ^= square.to_bb();
+            attackers |= self.xray_attackers(board, &to, occupancy);
+            attackers &= occupancy;
+        }
+
+        result
+    }
+
+    // The occupancy after the move was made, and the value it captures
+    // (including the gain of a promotion).
+    fn see_start(board: &Board, m: &Move) -> (BitBoard, i32) {
+        let mut occupancy = board.occupancy(Sides::BOTH) ^ m.from().to_bb();
+        let mut value = SEE_VALUES[m.captured()];
+
+        if m.en_passant() {
+            occupancy ^= Square(m.to().0 ^ 8).to_bb();
+            value = SEE_VALUES[Pieces::PAWN];
+        }
+        if m.promoted() != Pieces::NONE {
+            value += SEE_VALUES[m.promoted()] - SEE_VALUES[Pieces::PAWN];
+        }
+
+        (occupancy | m.to().to_bb(), value)
+    }
+
+    // The piece standing on the to-square after the move.
+    fn see_piece(m: &Move) -> usize {
+        if m.promoted() != Pieces::NONE {
+            m.promoted()
+        } else {
+            m.piece()
+        }
+    }
+
+    // Sliders that attack the square through the given occupancy. Called
+    // after a piece was removed, to find attackers that were behind it.
+    fn xray_attackers(&self, board: &Board, square: &Square, occupancy: BitBoard) -> BitBoard {
+        let w = &board.pieces[Sides::WHITE];
+        let b = &board.pieces[Sides::BLACK];
+        let rooks = w[Pieces::ROOK] | w[Pieces::QUEEN] | b[Pieces::ROOK] | b[Pieces::QUEEN];
+        let bishops = w[Pieces::BISHOP] | w[Pieces::QUEEN] | b[Pieces::BISHOP] | b[Pieces::QUEEN];
+
+        (self.get_slider_attacks(Pieces::ROOK, square, occupancy) & rooks)
+            | (self.get_slider_attacks(Pieces::BISHOP, square, occupancy) & bishops)
+    }
+
+    fn least_valuable_attacker(
+        board: &Board,
+        attackers: BitBoard,
+        side: Side,
+    ) -> Option<(usize, Square)> {
+        LEAST_VALUABLE_FIRST.iter().find_map(|&piece| {
+            let mut bb = attackers & board.pieces[side][piece];
+            if bb != BitBoard::EMPTY {
+                Some((piece, BitBoard::next(&mut bb)))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::movegen::MoveGenerator;
+    use crate::movegen::bit_move::Move;
+
+    // Positions with a move in them, and the SEE value of the move.
+    const CASES: [(&str, &str, i32); 6] = [
+        // Pawn takes an undefended knight.
+        ("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5", 300),
+        // Queen takes a pawn defended by a pawn.
+        ("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1", "d2d5", -800),
+        // Both sides have a rook behind the first capturer.
+        ("3r1k2/8/8/3r4/8/8/3Q4/3RK3 w - - 0 1", "d2d5", 100),
+        // The rook behind the pawn captured en passant joins the exchange.
+        ("4k3/2p5/8/3pP3/8/8/8/3RK3 w - d6 0 1", "e5d6", 100),
+        // Capture with promotion, and the rook takes the new queen.
+        ("rn2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q", 200),
+        // The king can't take back, because the second rook defends d7.
+        ("3k4/3p4/8/8/8/8/3R4/3RK3 w - - 0 1", "d2d7", 100),
+    ];
+
+    fn find_move(board: &mut Board, mg: &MoveGenerator, move_str: &str) -> Move {
+        board
+            .legal_moves(mg)
+            .into_iter()
+            .find(|m| m.as_string() == move_str)
+            .unwrap()
+    }
+
+    #[test]
+    fn see_values() {
+        let mg = MoveGenerator::new();
+        for (fen, move_str, expected) in CASES {
+            let mut board = Board::new();
+            board.fen_read(Some(fen)).unwrap();
+            let m = find_move(&mut board, &mg, move_str);
+
+            assert_eq!(mg.see(&board, m), expected, "{fen} {move_str}");
+        }
+    }
+
+    // see_ge() gives the same answer as comparing the exact value.
+    #[test]
+    fn see_ge_matches_see() {
+        let mg = MoveGenerator::new();
+        for (fen, move_str, _) in CASES {
+            let mut board = Board::new();
+            board.fen_read(Some(fen)).unwrap();
+            let m = find_move(&mut board, &mg, move_str);
+            let value = mg.see(&board, m);
+
+            for threshold in (-1500..=1500).step_by(50).chain([value, value + 1]) {
+                assert_eq!(
+                    mg.see_ge(&board, m, threshold),
+                    value >= threshold,
+                    "{fen} {move_str} {threshold}"
+                );
+            }
+        }
+    }
+}
@@ -3,10 +3,11 @@ use crate::{
     defs::{Piece, Pieces, Square},
 };
 
-use super::MoveGenerator;
+use super::{MoveGenerator, MoveType};
 
 impl MoveGenerator {
-    pub fn generate_rook_moves(&self, board: &Board, list: &mut MoveList) {
+    pub fn generate_rook_moves(&self, board: &Board, list: &mut MoveList, move_type: MoveType) {
+        let targets = Self::target_squares(board, move_type);
         let side = board.game_state.side_to_move as usize;
         let mut rooks = board.pieces[side][Pieces::ROOK];
         let occupancy = board.black_occupied() | board.white_occupied();
@@ -14,13 +15,7 @@ impl MoveGenerator {
         while rooks.0 > 0 {
             let from = BitBoard::next(&mut rooks);
             let index = self.rook_magics[from.0].get_index(occupancy);
-            let mut to_bb = self.rook[index];
-            // if white
-            if side == 0 {
-                to_bb &= !board.white_occupied()
-            } else {
-                to_bb &= !board.black_occupied()
-            }
+            let mut to_bb = self.rook[index] & targets;
 
             //println!("{}", to_bb);
             while to_bb.0 > 0 {
@@ -31,20 +26,15 @@ impl MoveGenerator {
         }
     }
 
-    pub fn generate_bishop_moves(&self, board: &Board, list: &mut MoveList) {
+    pub fn generate_bishop_moves(&self, board: &Board, list: &mut MoveList, move_type: MoveType) {
+        let targets = Self::target_squares(board, move_type);
         let side = board.game_state.side_to_move as usize;
         let mut bishops = board.pieces[side][Pieces::BISHOP];
         let occupancy = board.black_occupied() | board.white_occupied();
         while bishops.0 > 0 {
             let from = BitBoard::next(&mut bishops);
             let index = self.bishop_magics[from.0].get_index(occupancy);
-            let mut to_bb = self.bishop[index];
-            // if white
-            if side == 0 {
-                to_bb &= !board.white_occupied()
-            } else {
-                to_bb &= !board.black_occupied()
-            }
+            let mut to_bb = self.bishop[index] & targets;
             while to_bb.0 > 0 {
                 let to = BitBoard::next(&mut to_bb);
                 //println!("Adding bishop move from {} to {}", from, to);
@@ -53,7 +43,8 @@ impl MoveGenerator {
         }
     }
 
-    pub fn generate_queen_moves(&self, board: &Board, list: &mut MoveList) {
+    pub fn generate_queen_moves(&self, board: &Board, list: &mut MoveList, move_type: MoveType) {
+        let targets = Self::target_squares(board, move_type);
         let side = board.game_state.side_to_move as usize;
         let mut queens = board.pieces[side][Pieces::QUEEN];
         let occupancy = board.black_occupied() | board.white_occupied();
@@ -61,13 +52,7 @@ impl MoveGenerator {
             let from = BitBoard::next(&mut queens);
             // Diagonal moves
             let mut index = self.bishop_magics[from.0].get_index(occupancy);
-            let mut to_bb = self.bishop[index];
-            // if white
-            if side == 0 {
-                to_bb &= !board.white_occupied()
-            } else {
-                to_bb &= !board.black_occupied()
-            }
+            let mut to_bb = self.bishop[index] & targets;
             while to_bb.0 > 0 {
                 let to = BitBoard::next(&mut to_bb);
                 //println!("Adding queen move from {} to {}", from, to);
@@ -75,13 +60,7 @@ impl MoveGenerator {
             }
             // Straight moves
             index = self.rook_magics[from.0].get_index(occupancy);
-            to_bb = self.rook[index];
-            // if white
-            if side == 0 {
-                to_bb &= !board.white_occupied()
-            } else {
-                to_bb &= !board.black_occupied()
-            }
+            to_bb = self.rook[index] & targets;
             while to_bb.0 > 0 {
                 let to = BitBoard::next(&mut to_bb);
                 //println!("Adding queen move from {} to {}", from, to);
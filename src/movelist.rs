@@ -34,6 +34,17 @@ impl MoveList {
         self.list[index as usize]
     }
 
+    // Return a mutable reference to the move at the given index, so its
+    // sort score can be set.
+    pub fn get_mut_move(&mut self, index: u8) -> &mut Move {
+        &mut self.list[index as usize]
+    }
+
+    // Swap the moves at the two given indices.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.list.swap(a, b);
+    }
+
     pub fn move_list_ok(&self) -> bool {
         let mut result: bool = true;
         if self.count > MAX_POSITION_MOVES.try_into().unwrap() {
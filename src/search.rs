@@ -1,72 +1,890 @@
-use crate::{board::Board, evaluate::evaluate_position, movegen::MoveGenerator, movelist::MoveList};
+mod ordering;
+mod pv;
+mod quiescence;
+mod reductions;
+mod time;
+pub mod transposition;
+
+use crate::{
+    board::Board,
+    defs::{MAX_DEPTH, MAX_PLY, NrOf, Sides},
+    evaluate::{evaluate_position, pawns::PawnHashTable},
+    movegen::MoveGenerator,
+    movegen::bit_move::Move,
+    movelist::MoveList,
+};
+use ordering::{HistoryTable, KillerMoves};
+use pv::PvTable;
+use quiescence::quiescence;
+use reductions::Reductions;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+use time::TimeManager;
+use transposition::{Bound, TranspositionTable};
+
+pub const INFINITY: i32 = 100000;
+pub const MATE: i32 = 99999;
+// Scores beyond this bound are mate scores.
+pub const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+
+// Captures losing more than this margin per ply of remaining depth are
+// pruned near the leaves.
+const SEE_PRUNING_DEPTH: i8 = 6;
+const SEE_PRUNING_MARGIN: i32 = 100;
+
+// Null-move pruning: the null move is searched with the depth reduced by
+// NULL_MOVE_REDUCTION plus one ply for every NULL_MOVE_DEPTH_DIVISOR plies of
+// remaining depth.
+const NULL_MOVE_MIN_DEPTH: i8 = 3;
+const NULL_MOVE_REDUCTION: i8 = 3;
+const NULL_MOVE_DEPTH_DIVISOR: i8 = 6;
+
+// Reverse futility pruning: a node is cut off when the static evaluation
+// beats beta by this margin per ply of remaining depth.
+const RFP_DEPTH: i8 = 6;
+const RFP_MARGIN: i32 = 80;
+
+// Futility pruning: close to the leaves, quiet moves are skipped when the
+// static evaluation plus this margin (indexed by depth) can't reach alpha.
+const FUTILITY_DEPTH: i8 = 2;
+const FUTILITY_MARGIN: [i32; FUTILITY_DEPTH as usize + 1] = [0, 150, 300];
+
+// Late move pruning: at low depth, quiet moves are skipped after this many
+// legal moves plus the depth squared.
+const LMP_DEPTH: i8 = 4;
+const LMP_BASE: usize = 3;
+
+// Late move reductions are only done from this depth on, and never for the
+// first moves in the list.
+const LMR_DEPTH: i8 = 3;
+const LMR_MOVES: usize = 3;
+
+// Aspiration windows: from this depth on, each iteration starts with a narrow
+// window around the score of the previous one. When the score falls outside
+// the window, it is widened by half its size until the score fits.
+const ASPIRATION_DEPTH: i8 = 4;
+const ASPIRATION_WINDOW: i32 = 25;
+
+// The time manager is polled once every this many nodes (plus one). Helper
+// threads report their node counts at the same interval.
+const CHECK_INTERVAL: u64 = 2047;
+
+pub const DEFAULT_THREADS: usize = 1;
+pub const MAX_THREADS: usize = 256;
+
+pub const DEFAULT_MULTI_PV: usize = 1;
+pub const MAX_MULTI_PV: usize = 256;
 
+// The limits given with the "go" command. Times are in milliseconds. A
+// quiet search doesn't print "info" lines. If "search_moves" isn't empty,
+// only those moves are searched at the root; they must be legal.
 pub struct SearchInfo {
     pub depth: i8,
-    pub time: u32,
-    pub increment: u32,
+    pub time: u64,
+    pub increment: u64,
+    pub moves_to_go: Option<u64>,
+    pub move_time: Option<u64>,
+    pub nodes: Option<u64>,
+    pub mate: Option<u32>,
+    pub infinite: bool,
+    pub ponder: bool,
+    pub quiet: bool,
+    pub search_moves: Vec<Move>,
 }
 
 impl SearchInfo {
     pub fn new() -> Self {
-        SearchInfo { depth: -1, time: 0, increment: 0 }
+        SearchInfo {
+            depth: -1,
+            time: 0,
+            increment: 0,
+            moves_to_go: None,
+            move_time: None,
+            nodes: None,
+            mate: None,
+            infinite: false,
+            ponder: false,
+            quiet: false,
+            search_moves: Vec::new(),
+        }
+    }
+}
+
+// The selective search techniques can each be switched off with a UCI
+// option, so their effect can be tested separately. "multi_pv" is the
+// number of best moves reported, each with its own principal variation.
+#[derive(Copy, Clone)]
+pub struct SearchOptions {
+    pub late_move_reductions: bool,
+    pub reverse_futility_pruning: bool,
+    pub futility_pruning: bool,
+    pub late_move_pruning: bool,
+    pub check_extensions: bool,
+    pub multi_pv: usize,
+}
+
+impl SearchOptions {
+    pub fn new() -> Self {
+        Self {
+            late_move_reductions: true,
+            reverse_futility_pruning: true,
+            futility_pruning: true,
+            late_move_pruning: true,
+            check_extensions: true,
+            multi_pv: DEFAULT_MULTI_PV,
+        }
+    }
+}
+
+// Signals sent by the UCI thread to a running search. "stop" ends the search
+// as soon as possible; "ponder" is set while the engine thinks on the
+// opponent's time, and cleared when the GUI sends "ponderhit".
+pub struct SearchSignals {
+    pub stop: AtomicBool,
+    pub ponder: AtomicBool,
+}
+
+impl SearchSignals {
+    pub fn new(ponder: bool) -> Self {
+        Self {
+            stop: AtomicBool::new(false),
+            ponder: AtomicBool::new(ponder),
+        }
+    }
+
+    pub fn stop_requested(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn pondering(&self) -> bool {
+        self.ponder.load(Ordering::Relaxed)
+    }
+}
+
+// Lazy SMP: the search runs the same iterative deepening in several threads
+// at once. The threads only communicate through the shared transposition
+// table; because they search at slightly different speeds and depths, they
+// fill it with results the others can use. The main thread decides when to
+// stop and reports the result; the helper threads stop when it does.
+pub struct SmpState {
+    stop: AtomicBool,
+    helper_nodes: AtomicU64,
+}
+
+impl SmpState {
+    fn new() -> Self {
+        Self {
+            stop: AtomicBool::new(false),
+            helper_nodes: AtomicU64::new(0),
+        }
+    }
+}
+
+// Statistics and state gathered while a search is running. Every thread has
+// its own.
+pub struct SearchData {
+    pub timer: TimeManager,
+    pub nodes: u64,
+    pub seldepth: usize,
+    pub pv: PvTable,
+    pub completed_depth: i8,
+    pub stopped: bool,
+    pub pondering: bool,
+    pub killers: KillerMoves,
+    pub history: HistoryTable,
+    pub reductions: Reductions,
+    pub pawn_table: PawnHashTable,
+    // The moves searched at the root (all legal moves, or those given with
+    // "go searchmoves"), minus the excluded ones: in MultiPV mode, the best
+    // moves of the earlier lines of the current iteration.
+    pub root_moves: Vec<Move>,
+    pub excluded: Vec<Move>,
+}
+
+impl SearchData {
+    pub fn new(timer: TimeManager, pondering: bool) -> Self {
+        Self {
+            timer,
+            nodes: 0,
+            seldepth: 0,
+            pv: PvTable::new(),
+            completed_depth: 0,
+            stopped: false,
+            pondering,
+            killers: [[Move::null(); 2]; MAX_PLY],
+            history: [[[0; NrOf::SQUARES]; NrOf::SQUARES]; Sides::BOTH],
+            reductions: Reductions::new(),
+            pawn_table: PawnHashTable::new(),
+            root_moves: Vec::new(),
+            excluded: Vec::new(),
+        }
+    }
+}
+
+// Everything alpha_beta() needs, bundled so the recursion doesn't have to
+// pass each of them separately.
+pub struct SearchRefs<'a> {
+    pub board: &'a mut Board,
+    pub mg: &'a MoveGenerator,
+    pub info: &'a SearchInfo,
+    pub options: &'a SearchOptions,
+    pub data: &'a mut SearchData,
+    pub tt: &'a TranspositionTable,
+    pub signals: &'a SearchSignals,
+    pub smp: &'a SmpState,
+    // 0 for the main thread, 1 and up for the helpers.
+    pub thread: usize,
+}
+
+impl SearchRefs<'_> {
+    fn main_thread(&self) -> bool {
+        self.thread == 0
+    }
+
+    // Only the main thread talks to the GUI.
+    fn reporting(&self) -> bool {
+        self.main_thread() && !self.info.quiet
+    }
+
+    // The nodes searched by all threads. Exact for the main thread itself;
+    // the helpers' counts lag a little.
+    fn total_nodes(&self) -> u64 {
+        self.data.nodes + self.smp.helper_nodes.load(Ordering::Relaxed)
+    }
+}
+
+// The outcome of a search: the move to play and its score and, if the
+// principal variation is long enough, the reply we expect so the GUI can
+// ponder on it.
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub ponder_move: Option<Move>,
+    pub score: i32,
+}
+
+// Poll the limits and flag the search as stopped when one is reached. The
+// node limit is checked at every node, the clock only every few thousand.
+// The first iteration is never aborted, so there is always a move to play.
+// Helper threads only stop when the main thread does.
+pub fn check_termination(refs: &mut SearchRefs) {
+    if !refs.main_thread() {
+        if refs.data.nodes & CHECK_INTERVAL == 0 {
+            refs.smp
+                .helper_nodes
+                .fetch_add(CHECK_INTERVAL + 1, Ordering::Relaxed);
+        }
+        if refs.smp.stop.load(Ordering::Relaxed) {
+            refs.data.stopped = true;
+        }
+        return;
+    }
+
+    let nodes = refs.total_nodes();
+    let data = &mut *refs.data;
+    if data.completed_depth == 0 {
+        return;
+    }
+
+    if refs.signals.stop_requested() {
+        data.stopped = true;
+        return;
+    }
+
+    // While pondering, the clock isn't running for us. After "ponderhit",
+    // our time starts counting.
+    if data.pondering {
+        if refs.signals.pondering() {
+            return;
+        }
+        data.pondering = false;
+        data.timer.restart();
+    }
+
+    if data.timer.out_of_nodes(nodes)
+        || (data.nodes & CHECK_INTERVAL == 0 && data.timer.hard_limit_reached())
+    {
+        data.stopped = true;
     }
 }
 
 pub fn alpha_beta(
-    board: &mut Board,
-    depth: i8,
+    refs: &mut SearchRefs,
+    mut depth: i8,
     mut alpha: i32,
     beta: i32,
-    info: &SearchInfo,
-    move_generator: &MoveGenerator,
+    ply: usize,
 ) -> i32 {
-    
-    if depth == 0 {
-        return evaluate_position(board);
+    refs.data.nodes += 1;
+    refs.data.seldepth = refs.data.seldepth.max(ply);
+    refs.data.pv.clear(ply);
+
+    check_termination(refs);
+    if refs.data.stopped {
+        return 0;
+    }
+
+    // Repetitions, the fifty-move rule and dead positions are draws. This
+    // isn't checked at the root, because a move must always be returned.
+    if ply > 0
+        && (refs.board.is_repetition()
+            || refs.board.is_fifty_move_draw(refs.mg)
+            || refs.board.is_insufficient_material())
+    {
+        return 0;
+    }
+
+    if ply >= MAX_PLY - 1 {
+        return evaluate_position(refs.board, refs.mg, &mut refs.data.pawn_table);
+    }
+
+    // Check extension: when in check, there are only a few replies, and
+    // the position is often critical, so it is searched one ply deeper.
+    let in_check = refs.board.in_check(refs.mg);
+    if in_check && refs.options.check_extensions {
+        depth += 1;
+    }
+
+    if depth <= 0 {
+        return quiescence(refs, alpha, beta, ply);
+    }
+
+    // See if this position was already searched deeply enough. At the root
+    // and in other PV nodes we always search, so a full principal variation
+    // is collected; a cutoff there would leave the PV ending at this node.
+    let pv_node = beta - alpha > 1;
+    let key = refs.board.game_state.zobrist_key;
+    let tt_entry = refs.tt.probe(key, ply);
+    if ply > 0
+        && !pv_node
+        && let Some(entry) = tt_entry
+        && entry.depth >= depth
+    {
+        match entry.bound {
+            Bound::Exact => return entry.score,
+            Bound::Lower if entry.score >= beta => return entry.score,
+            Bound::Upper if entry.score <= alpha => return entry.score,
+            _ => {}
+        }
+    }
+
+    // Mate distance pruning: even mating right now can't beat a shorter
+    // mate that was already found closer to the root.
+    alpha = alpha.max(-MATE + ply as i32);
+    let beta = beta.min(MATE - ply as i32 - 1);
+    if alpha >= beta {
+        return alpha;
+    }
+
+    let static_eval = if in_check {
+        -INFINITY
+    } else {
+        evaluate_position(refs.board, refs.mg, &mut refs.data.pawn_table)
+    };
+
+    // Reverse futility pruning: if the static evaluation is so far above
+    // beta that not even a bad move could bring it back below, the node
+    // fails high without searching.
+    if refs.options.reverse_futility_pruning
+        && ply > 0
+        && !pv_node
+        && !in_check
+        && depth <= RFP_DEPTH
+        && beta.abs() < MATE_BOUND
+        && static_eval - RFP_MARGIN * depth as i32 >= beta
+    {
+        return static_eval;
+    }
+
+    // Null-move pruning: if we could pass the turn and a reduced search
+    // still fails high, a real move will almost certainly do so as well.
+    // This is not true in zugzwang, where every move makes things worse;
+    // that happens mostly when only the king and pawns are left. Passing
+    // while in check is illegal, and two null moves in a row prove nothing.
+    // Only done in null-window nodes, so the principal variation is exact.
+    if ply > 0
+        && !in_check
+        && depth >= NULL_MOVE_MIN_DEPTH
+        && !pv_node
+        && !refs.board.last_move_null()
+        && refs
+            .board
+            .has_non_pawn_material(refs.board.game_state.side_to_move)
+        && static_eval >= beta
+    {
+        let reduction = NULL_MOVE_REDUCTION + depth / NULL_MOVE_DEPTH_DIVISOR;
+        refs.board.make_null();
+        let score = -alpha_beta(refs, depth - 1 - reduction, -beta, -beta + 1, ply + 1);
+        refs.board.unmake_null();
+
+        if refs.data.stopped {
+            return 0;
+        }
+
+        // A mate found after passing isn't proven, so it isn't returned.
+        if score >= beta {
+            return if score > MATE_BOUND { beta } else { score };
+        }
     }
-    let mut best_value = -99999;
+
+    // Futility pruning: at frontier nodes where the static evaluation is far
+    // below alpha, quiet moves are unlikely to help.
+    let futile = refs.options.futility_pruning
+        && ply > 0
+        && !pv_node
+        && !in_check
+        && depth <= FUTILITY_DEPTH
+        && alpha.abs() < MATE_BOUND
+        && static_eval + FUTILITY_MARGIN[depth as usize] <= alpha;
+
+    let alpha_orig = alpha;
+    let mut best_value = -INFINITY;
+    let mut best_move: Option<Move> = None;
+    let mut legal_moves = 0;
 
     let mut list = MoveList::new();
-    move_generator.generate_all_moves(board, &mut list);
+    refs.mg.generate_all_moves(refs.board, &mut list);
+    let side = refs.board.game_state.side_to_move;
+    ordering::score_moves(
+        refs.board,
+        refs.mg,
+        &mut list,
+        tt_entry.and_then(|e| e.best_move()),
+        &refs.data.killers[ply],
+        &refs.data.history,
+        side,
+    );
 
     for i in 0..list.len() {
+        ordering::pick_move(&mut list, i);
         let m = list.get_move(i);
+
+        if ply == 0 && !root_move(refs.data, m) {
+            continue;
+        }
+
+        // SEE pruning: close to the leaves, captures that lose a lot of
+        // material are not worth searching. At least one legal move must
+        // have been searched, or a checkmate could be reported by mistake.
+        if ply > 0
+            && !in_check
+            && legal_moves > 0
+            && depth <= SEE_PRUNING_DEPTH
+            && m.is_capture()
+            && !refs
+                .mg
+                .see_ge(refs.board, m, -SEE_PRUNING_MARGIN * depth as i32)
+        {
+            continue;
+        }
+
         // Only proceed if the move is legal. make() returns false for illegal moves
         // and already handles unmaking them internally, so we only call unmake()
         // for legal moves to maintain balanced push/pop operations on the history stack.
-        if board.make(m, move_generator) {
-            let score = -alpha_beta(board, depth - 1, -beta, -alpha, info, move_generator);
-            board.unmake();
+        if refs.board.make(m, refs.mg) {
+            legal_moves += 1;
+            let gives_check = refs.board.in_check(refs.mg);
+            let quiet = m.is_quiet() && !gives_check;
+
+            // Futility and late move pruning skip quiet moves that are
+            // unlikely to raise alpha. The moves are legal, so they still
+            // count for checkmate and stalemate detection.
+            let late = refs.options.late_move_pruning
+                && !pv_node
+                && !in_check
+                && depth <= LMP_DEPTH
+                && legal_moves > LMP_BASE + (depth as usize * depth as usize);
+            if quiet && legal_moves > 1 && (futile || late) {
+                refs.board.unmake();
+                continue;
+            }
+
+            // Principal Variation Search: with good move ordering, the first
+            // move is the best one. The other moves are searched with a null
+            // window, only to prove that they are worse. If that fails, the
+            // move is searched again with the full window. Late quiet moves
+            // are first searched with reduced depth; if one of them beats
+            // alpha after all, it is searched again at full depth.
+            let score = if legal_moves == 1 {
+                -alpha_beta(refs, depth - 1, -beta, -alpha, ply + 1)
+            } else {
+                let mut reduction = 0;
+                if refs.options.late_move_reductions
+                    && quiet
+                    && !in_check
+                    && depth >= LMR_DEPTH
+                    && legal_moves > LMR_MOVES
+                {
+                    reduction = refs.data.reductions.get(depth, legal_moves);
+                    if pv_node {
+                        reduction -= 1;
+                    }
+                    reduction = reduction.clamp(0, depth - 2);
+                }
+
+                let mut score =
+                    -alpha_beta(refs, depth - 1 - reduction, -alpha - 1, -alpha, ply + 1);
+                if score > alpha && reduction > 0 {
+                    score = -alpha_beta(refs, depth - 1, -alpha - 1, -alpha, ply + 1);
+                }
+                if score > alpha && score < beta {
+                    score = -alpha_beta(refs, depth - 1, -beta, -alpha, ply + 1);
+                }
+                score
+            };
+            refs.board.unmake();
+
+            // The score of an aborted search is meaningless.
+            if refs.data.stopped {
+                return 0;
+            }
 
             if score > best_value {
                 best_value = score;
+                best_move = Some(m);
             }
             if score > alpha {
                 alpha = score;
+                refs.data.pv.update(ply, m);
             }
             if score >= beta {
-                return best_value
+                if m.is_quiet() {
+                    ordering::store_killer(&mut refs.data.killers[ply], m);
+                    ordering::update_history(&mut refs.data.history, side, m, depth);
+                }
+                if !(ply == 0 && root_filtered(refs)) {
+                    refs.tt
+                        .store(key, depth, best_value, Bound::Lower, best_move, ply);
+                }
+                return best_value;
             }
         }
     }
+
+    // Without legal moves, the game is over: checkmate if the side to move
+    // is in check, stalemate otherwise. Mates closer to the root score
+    // higher, so the search prefers the shortest mate.
+    if legal_moves == 0 {
+        return if in_check { -MATE + ply as i32 } else { 0 };
+    }
+
+    let bound = if best_value > alpha_orig {
+        Bound::Exact
+    } else {
+        Bound::Upper
+    };
+    if !(ply == 0 && root_filtered(refs)) {
+        refs.tt.store(key, depth, best_value, bound, best_move, ply);
+    }
     best_value
 }
 
-pub fn search_position(board: &mut Board, info: &SearchInfo, move_generator: &MoveGenerator) {
-    let start_time = std::time::Instant::now();
-    let search_time = info.time / 20 + info.increment / 2;
-    for curr_depth in 1..=info.depth {
-        // Use curr_depth instead of info.depth to implement iterative deepening correctly.
-        // This ensures each iteration searches to the appropriate depth level.
-        let best_score = alpha_beta(board, curr_depth, -100000, 100000, info, move_generator);
-    
-        println!("info depth {} score {}", curr_depth, best_score);
-        let _ = std::io::stdout().flush();
-        let current_time = std::time::Instant::now();
-        if current_time.duration_since(start_time).as_millis() as u32 >= search_time {
+fn root_move(data: &SearchData, m: Move) -> bool {
+    let same = |r: &Move| r.get_move() == m.get_move();
+    data.root_moves.iter().any(same) && !data.excluded.iter().any(same)
+}
+
+// When some root moves are left out, the result of the root search is not
+// the result for the position, so it can't be stored in the TT.
+fn root_filtered(refs: &SearchRefs) -> bool {
+    !refs.data.excluded.is_empty() || !refs.info.search_moves.is_empty()
+}
+
+pub fn search_position(
+    board: &mut Board,
+    info: &SearchInfo,
+    options: &SearchOptions,
+    move_generator: &MoveGenerator,
+    tt: &mut TranspositionTable,
+    signals: &SearchSignals,
+    threads: usize,
+) -> SearchResult {
+    tt.new_search();
+    let tt = &*tt;
+    let smp = SmpState::new();
+    let mut root_moves = board.legal_moves(move_generator);
+    if !info.search_moves.is_empty() {
+        root_moves.retain(|m| {
+            info.search_moves
+                .iter()
+                .any(|s| s.get_move() == m.get_move())
+        });
+    }
+
+    let best_line = thread::scope(|s| {
+        for thread in 1..threads {
+            let mut board = board.clone();
+            let smp = &smp;
+            let root_moves = root_moves.clone();
+            s.spawn(move || {
+                let mut data = SearchData::new(TimeManager::new(info), false);
+                data.root_moves = root_moves;
+                let mut refs = SearchRefs {
+                    board: &mut board,
+                    mg: move_generator,
+                    info,
+                    options,
+                    data: &mut data,
+                    tt,
+                    signals,
+                    smp,
+                    thread,
+                };
+                iterative_deepening(&mut refs);
+                smp.helper_nodes
+                    .fetch_add(data.nodes & CHECK_INTERVAL, Ordering::Relaxed);
+            });
+        }
+
+        let mut data = SearchData::new(TimeManager::new(info), signals.pondering());
+        data.root_moves = root_moves;
+        let mut refs = SearchRefs {
+            board: &mut *board,
+            mg: move_generator,
+            info,
+            options,
+            data: &mut data,
+            tt,
+            signals,
+            smp: &smp,
+            thread: 0,
+        };
+        let best_line = iterative_deepening(&mut refs);
+
+        // In infinite and ponder mode, "bestmove" may only be sent after the
+        // GUI says so, even if the search finished early. The helpers keep
+        // searching in the meantime.
+        while (info.infinite || signals.pondering()) && !signals.stop_requested() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        smp.stop.store(true, Ordering::Relaxed);
+        best_line
+    });
+
+    let (score, line) = best_line;
+    let best_move = line.first().copied();
+    let ponder_move = match line.get(1) {
+        Some(m) => Some(*m),
+        None => best_move.and_then(|m| ponder_from_tt(board, move_generator, tt, m)),
+    };
+
+    SearchResult {
+        best_move,
+        ponder_move,
+        score,
+    }
+}
+
+// Iterative deepening: search the root one ply deeper each time, until a
+// limit is reached. Returns the score and the principal variation of the
+// best line of the last completed iteration. Helper threads search every
+// other iteration one ply deeper than the main thread, so they don't all
+// search the same tree.
+//
+// In MultiPV mode, each iteration searches the root once per line; every
+// search excludes the best moves of the lines before it. Only the main
+// thread does this; the helpers just search for the best move.
+fn iterative_deepening(refs: &mut SearchRefs) -> (i32, Vec<Move>) {
+    let multi_pv = if refs.main_thread() {
+        refs.options
+            .multi_pv
+            .clamp(1, refs.data.root_moves.len().max(1))
+    } else {
+        1
+    };
+    let mut lines: Vec<(i32, Vec<Move>)> = vec![(0, Vec::new()); multi_pv];
+    let skip = (refs.thread % 2) as i8;
+
+    'deepening: for curr_depth in 1..=refs.info.depth {
+        let depth = (curr_depth + skip).min(MAX_DEPTH);
+        refs.data.seldepth = 0;
+        refs.data.excluded.clear();
+
+        for (pv_index, (previous_score, previous_line)) in lines.iter_mut().enumerate() {
+            let score = aspiration_search(refs, pv_index, depth, *previous_score, previous_line);
+
+            // An aborted search is incomplete; the result of the previous
+            // iteration is used instead.
+            if refs.data.stopped {
+                break 'deepening;
+            }
+
+            let line = refs.data.pv.line().to_vec();
+            if let Some(&m) = line.first() {
+                refs.data.excluded.push(m);
+            }
+            *previous_score = score;
+            *previous_line = line;
+        }
+
+        // A later line can come out better than an earlier one, because
+        // the search isn't exact.
+        lines.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        refs.data.completed_depth = depth;
+        if !refs.main_thread() {
+            continue;
+        }
+
+        if refs.reporting() {
+            for (pv_index, (score, line)) in lines.iter().enumerate() {
+                print_info(refs, pv_index, depth, *score, Bound::Exact, line);
+            }
+        }
+
+        check_termination(refs);
+        let out_of_time =
+            !refs.data.pondering && refs.data.timer.soft_limit_reached(refs.total_nodes());
+        if refs.data.stopped || out_of_time || mate_found(refs.info, lines[0].0) {
             break;
         }
-    //return best_score
     }
-}
\ No newline at end of file
+
+    lines.swap_remove(0)
+}
+
+// Search the root with a window around the score of the previous iteration.
+// A score outside the window is only a bound, so the root is searched again
+// with a wider window; each re-search is reported to the GUI with that bound.
+// Mate scores are far outside any window, so they get the full one at once.
+fn aspiration_search(
+    refs: &mut SearchRefs,
+    pv_index: usize,
+    depth: i8,
+    previous_score: i32,
+    previous_line: &[Move],
+) -> i32 {
+    if depth < ASPIRATION_DEPTH || previous_score.abs() > MATE_BOUND {
+        return alpha_beta(refs, depth, -INFINITY, INFINITY, 0);
+    }
+
+    let mut delta = ASPIRATION_WINDOW;
+    let mut alpha = (previous_score - delta).max(-INFINITY);
+    let mut beta = (previous_score + delta).min(INFINITY);
+
+    loop {
+        let score = alpha_beta(refs, depth, alpha, beta, 0);
+        if refs.data.stopped {
+            return score;
+        }
+
+        if score <= alpha {
+            // Fail low: no move reached alpha, so there is no new PV yet.
+            if refs.reporting() {
+                print_info(refs, pv_index, depth, score, Bound::Upper, previous_line);
+            }
+            beta = (alpha + beta) / 2;
+            alpha = (score - delta).max(-INFINITY);
+        } else if score >= beta {
+            // Fail high: the move that beat beta is at the head of the PV.
+            if refs.reporting() {
+                let line = refs.data.pv.line().to_vec();
+                print_info(refs, pv_index, depth, score, Bound::Lower, &line);
+            }
+            beta = (score + delta).min(INFINITY);
+        } else {
+            return score;
+        }
+
+        delta += delta / 2;
+    }
+}
+
+// A transposition table cutoff directly below the root leaves a PV with only
+// one move. In that case the expected reply can usually still be found in the
+// table. It is only used if it is a legal move in the resulting position.
+fn ponder_from_tt(
+    board: &mut Board,
+    mg: &MoveGenerator,
+    tt: &TranspositionTable,
+    best_move: Move,
+) -> Option<Move> {
+    if !board.make(best_move, mg) {
+        return None;
+    }
+
+    let mut ponder_move = None;
+    if let Some(tt_move) = tt
+        .probe(board.game_state.zobrist_key, 1)
+        .and_then(|e| e.best_move())
+    {
+        let mut list = MoveList::new();
+        mg.generate_all_moves(board, &mut list);
+        for i in 0..list.len() {
+            let m = list.get_move(i);
+            if m.get_move() == tt_move.get_move() && board.make(m, mg) {
+                board.unmake();
+                ponder_move = Some(m);
+                break;
+            }
+        }
+    }
+
+    board.unmake();
+    ponder_move
+}
+
+// Report a completed iteration, or the bound found by an aspiration
+// re-search, to the GUI. In MultiPV mode, each line is numbered.
+fn print_info(
+    refs: &SearchRefs,
+    pv_index: usize,
+    depth: i8,
+    score: i32,
+    bound: Bound,
+    pv: &[Move],
+) {
+    let elapsed = refs.data.timer.elapsed();
+    let nodes = refs.total_nodes();
+    let score = match bound {
+        Bound::Lower => format!("{} lowerbound", format_score(score)),
+        Bound::Upper => format!("{} upperbound", format_score(score)),
+        _ => format_score(score),
+    };
+    let nps = (nodes * 1000) / elapsed.max(1);
+    let pv_string = pv
+        .iter()
+        .map(|m| m.as_string())
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let multi_pv = if refs.options.multi_pv > 1 {
+        format!(" multipv {}", pv_index + 1)
+    } else {
+        String::new()
+    };
+
+    println!(
+        "info depth {}{} seldepth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        depth,
+        multi_pv,
+        refs.data.seldepth,
+        score,
+        nodes,
+        nps,
+        refs.tt.hash_full(),
+        elapsed,
+        pv_string
+    );
+    let _ = std::io::stdout().flush();
+}
+
+// With "go mate N", the search ends as soon as a mate in N moves or less
+// has been found.
+fn mate_found(info: &SearchInfo, score: i32) -> bool {
+    info.mate
+        .is_some_and(|moves| score > MATE_BOUND && (MATE - score + 1) / 2 <= moves as i32)
+}
+
+// Scores are reported in centipawns, or as a mate distance in moves (not
+// plies). A negative distance means the engine is getting mated.
+fn format_score(score: i32) -> String {
+    if score > MATE_BOUND {
+        format!("mate {}", (MATE - score + 1) / 2)
+    } else if score < -MATE_BOUND {
+        format!("mate {}", -(MATE + score) / 2)
+    } else {
+        format!("cp {}", score)
+    }
+}
@@ -0,0 +1,132 @@
+use crate::board::Board;
+use crate::defs::{MAX_PLY, NrOf, Pieces, Sides};
+use crate::movegen::MoveGenerator;
+use crate::movegen::bit_move::Move;
+use crate::movelist::MoveList;
+
+// Move ordering. Every move gets a sort score, and the search then picks the
+// move with the highest score that wasn't tried yet. Because most nodes end
+// in a cutoff after a few moves, the list is never fully sorted.
+//
+// Order: the transposition table move, captures that don't lose material
+// (according to SEE) and queen promotions by MVV-LVA, the two killer moves
+// of the ply, the remaining quiet moves by their history score, and finally
+// the losing captures by their SEE value.
+
+const TT_MOVE_SCORE: u32 = 10_000_000;
+const CAPTURE_SCORE: u32 = 5_000_000;
+const KILLER_SCORE: [u32; 2] = [4_000_000, 3_900_000];
+// History scores are kept below the killer scores, but above the scores of
+// losing captures, which are below LOSING_CAPTURE_MAX because their SEE
+// value is negative.
+const HISTORY_MAX: u32 = 1_000_000;
+const LOSING_CAPTURE_MAX: u32 = 1_000;
+
+// Most Valuable Victim, Least Valuable Attacker, indexed by piece type:
+// K, Q, R, B, N, P, NONE.
+const VICTIM: [u32; NrOf::PIECE_TYPES + 1] = [0, 50, 40, 30, 30, 10, 0];
+const ATTACKER: [u32; NrOf::PIECE_TYPES + 1] = [6, 5, 4, 3, 2, 1, 0];
+
+pub type KillerMoves = [[Move; 2]; MAX_PLY];
+pub type HistoryTable = [[[u32; NrOf::SQUARES]; NrOf::SQUARES]; Sides::BOTH];
+
+pub fn mvv_lva(m: Move) -> u32 {
+    let victim = if m.en_passant() {
+        Pieces::PAWN
+    } else {
+        m.captured()
+    };
+    VICTIM[victim] * 10 + ATTACKER[Pieces::KING] - ATTACKER[m.piece()]
+}
+
+// Give each move in the list its sort score.
+pub fn score_moves(
+    board: &Board,
+    mg: &MoveGenerator,
+    list: &mut MoveList,
+    tt_move: Option<Move>,
+    killers: &[Move; 2],
+    history: &HistoryTable,
+    side: usize,
+) {
+    let tt_move = tt_move.map(|m| m.get_move());
+
+    for i in 0..list.len() {
+        let m = list.get_mut_move(i);
+        let score = if Some(m.get_move()) == tt_move {
+            TT_MOVE_SCORE
+        } else if m.is_capture() {
+            if mg.see_ge(board, *m, 0) {
+                CAPTURE_SCORE + mvv_lva(*m)
+            } else {
+                losing_capture_score(mg.see(board, *m))
+            }
+        } else if m.promoted() == Pieces::QUEEN {
+            CAPTURE_SCORE + VICTIM[Pieces::QUEEN]
+        } else if m.get_move() == killers[0].get_move() {
+            KILLER_SCORE[0]
+        } else if m.get_move() == killers[1].get_move() {
+            KILLER_SCORE[1]
+        } else {
+            LOSING_CAPTURE_MAX + history[side][m.from().0][m.to().0]
+        };
+        m.set_sort_score(score);
+    }
+}
+
+// Losing captures are tried in order of how much they lose.
+fn losing_capture_score(see: i32) -> u32 {
+    (LOSING_CAPTURE_MAX as i32 + see).max(0) as u32
+}
+
+// Give captures in the list their MVV-LVA score, for the quiescence search.
+pub fn score_captures(list: &mut MoveList) {
+    for i in 0..list.len() {
+        let m = list.get_mut_move(i);
+        let score = if m.promoted() == Pieces::QUEEN {
+            mvv_lva(*m) + VICTIM[Pieces::QUEEN] * 10
+        } else {
+            mvv_lva(*m)
+        };
+        m.set_sort_score(score);
+    }
+}
+
+// Swap the best scoring move from "start" onward into "start".
+pub fn pick_move(list: &mut MoveList, start: u8) {
+    let mut best = start;
+    let mut best_score = list.get_move(start).get_sort_score();
+    for i in (start + 1)..list.len() {
+        let score = list.get_move(i).get_sort_score();
+        if score > best_score {
+            best = i;
+            best_score = score;
+        }
+    }
+    list.swap(start as usize, best as usize);
+}
+
+// A quiet move that caused a beta cutoff is remembered as a killer for this
+// ply; it will probably cause a cutoff in sibling positions as well.
+pub fn store_killer(killers: &mut [Move; 2], m: Move) {
+    if killers[0].get_move() != m.get_move() {
+        killers[1] = killers[0];
+        killers[0] = m;
+    }
+}
+
+// Reward a quiet move that caused a beta cutoff. Deeper cutoffs are worth
+// more. When a score gets too large, all scores are halved, so older
+// information gradually loses weight.
+pub fn update_history(history: &mut HistoryTable, side: usize, m: Move, depth: i8) {
+    let entry = &mut history[side][m.from().0][m.to().0];
+    *entry += (depth as u32) * (depth as u32);
+
+    if *entry >= HISTORY_MAX {
+        history
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .for_each(|score| *score /= 2);
+    }
+}
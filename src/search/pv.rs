@@ -0,0 +1,47 @@
+use crate::defs::MAX_PLY;
+use crate::movegen::bit_move::Move;
+
+// The principal variation is collected in a triangular table. Row "ply"
+// holds the best line found from that ply onward; when a move raises alpha,
+// the row is rebuilt from that move followed by the row of the next ply.
+// Row 0 therefore holds the PV of the whole search once the root returns.
+pub struct PvTable {
+    moves: Vec<[Move; MAX_PLY]>,
+    length: [usize; MAX_PLY],
+}
+
+impl PvTable {
+    pub fn new() -> Self {
+        Self {
+            moves: vec![[Move::null(); MAX_PLY]; MAX_PLY],
+            length: [0; MAX_PLY],
+        }
+    }
+
+    // Start a new, empty line at the given ply.
+    pub fn clear(&mut self, ply: usize) {
+        self.length[ply] = 0;
+    }
+
+    // Store "m" as the best move at "ply", followed by the line of the
+    // next ply.
+    pub fn update(&mut self, ply: usize, m: Move) {
+        let child_length = if ply + 1 < MAX_PLY {
+            self.length[ply + 1]
+        } else {
+            0
+        };
+        let length = (child_length + 1).min(MAX_PLY - ply);
+
+        self.moves[ply][ply] = m;
+        for i in 1..length {
+            self.moves[ply][ply + i] = self.moves[ply + 1][ply + i];
+        }
+        self.length[ply] = length;
+    }
+
+    // The principal variation starting at the root.
+    pub fn line(&self) -> &[Move] {
+        &self.moves[0][..self.length[0]]
+    }
+}
@@ -0,0 +1,121 @@
+use crate::defs::{MAX_PLY, Pieces};
+use crate::evaluate::{PIECE_VALUES, evaluate_position};
+use crate::movegen::bit_move::Move;
+use crate::movelist::MoveList;
+use crate::search::{MATE, SearchRefs, check_termination, ordering};
+
+// Safety margin for delta pruning: a capture is skipped if even winning the
+// captured piece plus this margin can't bring the score up to alpha.
+const DELTA_MARGIN: i32 = 200;
+
+// The quiescence search continues after the main search reaches depth 0,
+// but only searches captures and queen promotions until the position is
+// quiet. This avoids the horizon effect, where the main search stops right
+// before the opponent recaptures. The side to move may also decline to
+// capture ("stand pat"), so the static evaluation is a lower bound.
+//
+// A side in check can't stand pat: all evasions are searched, and without
+// any, it is checkmated. The main search normally extends checks, so this
+// only happens with the check extension switched off, or after a capture
+// that gives check.
+pub fn quiescence(refs: &mut SearchRefs, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+    refs.data.nodes += 1;
+    refs.data.seldepth = refs.data.seldepth.max(ply);
+    refs.data.pv.clear(ply);
+
+    check_termination(refs);
+    if refs.data.stopped {
+        return 0;
+    }
+
+    if ply >= MAX_PLY - 1 {
+        return evaluate_position(refs.board, refs.mg, &mut refs.data.pawn_table);
+    }
+
+    let in_check = refs.board.in_check(refs.mg);
+    let mut list = MoveList::new();
+    let stand_pat = if in_check {
+        refs.mg.generate_all_moves(refs.board, &mut list);
+        -MATE + ply as i32
+    } else {
+        let stand_pat = evaluate_position(refs.board, refs.mg, &mut refs.data.pawn_table);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+
+        // If not even winning a queen helps, none of the captures will.
+        if stand_pat + PIECE_VALUES[Pieces::QUEEN] + DELTA_MARGIN <= alpha {
+            return stand_pat;
+        }
+
+        refs.mg.generate_captures(refs.board, &mut list);
+        stand_pat
+    };
+
+    if stand_pat > alpha {
+        alpha = stand_pat;
+    }
+
+    // In check, the best value stays the mate score if no evasion is legal.
+    let mut best_value = stand_pat;
+    ordering::score_captures(&mut list);
+
+    for i in 0..list.len() {
+        ordering::pick_move(&mut list, i);
+        let m = list.get_move(i);
+
+        // Evasions are never pruned.
+        if !in_check && !worth_searching(refs, m, stand_pat, alpha) {
+            continue;
+        }
+
+        if refs.board.make(m, refs.mg) {
+            let score = -quiescence(refs, -beta, -alpha, ply + 1);
+            refs.board.unmake();
+
+            if refs.data.stopped {
+                return 0;
+            }
+
+            if score > best_value {
+                best_value = score;
+            }
+            if score > alpha {
+                alpha = score;
+                refs.data.pv.update(ply, m);
+            }
+            if score >= beta {
+                break;
+            }
+        }
+    }
+
+    best_value
+}
+
+// Whether a capture or promotion can be expected to raise alpha.
+fn worth_searching(refs: &SearchRefs, m: Move, stand_pat: i32, alpha: i32) -> bool {
+    let promoted = m.promoted();
+
+    // Underpromotions are hardly ever better than a queen.
+    if promoted != Pieces::NONE && promoted != Pieces::QUEEN {
+        return false;
+    }
+
+    // Delta pruning: skip captures that can't raise alpha.
+    let captured = if m.en_passant() {
+        Pieces::PAWN
+    } else {
+        m.captured()
+    };
+    let mut gain = PIECE_VALUES[captured];
+    if promoted == Pieces::QUEEN {
+        gain += PIECE_VALUES[Pieces::QUEEN] - PIECE_VALUES[Pieces::PAWN];
+    }
+    if stand_pat + gain + DELTA_MARGIN <= alpha {
+        return false;
+    }
+
+    // Captures that lose material according to SEE are not searched.
+    refs.mg.see_ge(refs.board, m, 0)
+}
@@ -0,0 +1,35 @@
+use crate::defs::MAX_DEPTH;
+
+// Late move reductions. Moves late in the move list rarely turn out to be
+// the best, so they are searched with less depth. The reduction grows with
+// the logarithm of both the remaining depth and the move number.
+
+// Moves beyond this number all get the same reduction.
+const MAX_MOVE_NUMBER: usize = 64;
+
+const BASE: f64 = 0.75;
+const DIVISOR: f64 = 2.25;
+
+pub struct Reductions {
+    table: Vec<[i8; MAX_MOVE_NUMBER]>,
+}
+
+impl Reductions {
+    pub fn new() -> Self {
+        let mut table = vec![[0; MAX_MOVE_NUMBER]; MAX_DEPTH as usize + 1];
+        for (depth, row) in table.iter_mut().enumerate().skip(1) {
+            for (move_number, reduction) in row.iter_mut().enumerate().skip(1) {
+                let r = BASE + (depth as f64).ln() * (move_number as f64).ln() / DIVISOR;
+                *reduction = r as i8;
+            }
+        }
+        Self { table }
+    }
+
+    // The reduction for the n-th legal move (counting from 1) at the given
+    // remaining depth.
+    pub fn get(&self, depth: i8, move_number: usize) -> i8 {
+        let depth = (depth.max(0) as usize).min(MAX_DEPTH as usize);
+        self.table[depth][move_number.min(MAX_MOVE_NUMBER - 1)]
+    }
+}
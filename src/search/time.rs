@@ -0,0 +1,85 @@
+use crate::search::SearchInfo;
+use std::time::Instant;
+
+// Time reserved for communication with the GUI, so we don't lose on time
+// because of lag.
+const MOVE_OVERHEAD: u64 = 30;
+
+// If the GUI doesn't send "movestogo", assume the game lasts this many more
+// moves.
+const DEFAULT_MOVES_TO_GO: u64 = 25;
+
+// How far the search may overrun its planned time when an iteration is
+// still running, as a multiple of the soft limit.
+const HARD_LIMIT_FACTOR: u64 = 4;
+
+// The time manager decides how long the engine may think about a move.
+// There are two limits: after the soft limit, no new iteration is started;
+// when the hard limit is reached, the running iteration is aborted. A node
+// limit is treated like a hard limit.
+pub struct TimeManager {
+    start: Instant,
+    soft_limit: Option<u64>,
+    hard_limit: Option<u64>,
+    node_limit: Option<u64>,
+}
+
+impl TimeManager {
+    pub fn new(info: &SearchInfo) -> Self {
+        let (soft_limit, hard_limit) = if info.infinite {
+            (None, None)
+        } else if let Some(move_time) = info.move_time {
+            let limit = move_time.saturating_sub(MOVE_OVERHEAD).max(1);
+            (Some(limit), Some(limit))
+        } else if info.time > 0 {
+            Self::allocate(info)
+        } else {
+            (None, None)
+        };
+
+        Self {
+            start: Instant::now(),
+            soft_limit,
+            hard_limit,
+            node_limit: if info.infinite { None } else { info.nodes },
+        }
+    }
+
+    // Divide the remaining time over the moves still to be played, and add
+    // most of the increment. The hard limit allows the search to use a few
+    // times that, but never more than most of the clock.
+    fn allocate(info: &SearchInfo) -> (Option<u64>, Option<u64>) {
+        let available = info.time.saturating_sub(MOVE_OVERHEAD).max(1);
+        let moves_to_go = info.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).clamp(1, 50);
+        let maximum = available * 4 / 5;
+
+        let soft = (available / moves_to_go + info.increment * 3 / 4).min(maximum);
+        let hard = (soft * HARD_LIMIT_FACTOR).min(maximum);
+
+        (Some(soft.max(1)), Some(hard.max(1)))
+    }
+
+    // Start counting from now, for example after a "ponderhit".
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+    }
+
+    pub fn elapsed(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    // Checked between iterations: is there enough time left to start the
+    // next one?
+    pub fn soft_limit_reached(&self, nodes: u64) -> bool {
+        self.soft_limit.is_some_and(|limit| self.elapsed() >= limit) || self.out_of_nodes(nodes)
+    }
+
+    // Checked during the search: must the current iteration be aborted?
+    pub fn hard_limit_reached(&self) -> bool {
+        self.hard_limit.is_some_and(|limit| self.elapsed() >= limit)
+    }
+
+    pub fn out_of_nodes(&self, nodes: u64) -> bool {
+        self.node_limit.is_some_and(|limit| nodes >= limit)
+    }
+}
@@ -0,0 +1,289 @@
+use crate::movegen::bit_move::Move;
+use crate::search::MATE_BOUND;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// The transposition table stores the results of earlier searches, indexed by
+// the Zobrist key of the position. It is organised in buckets; the number of
+// buckets is always a power of two, so the index is found by masking the
+// lower bits of the key.
+//
+// All search threads share the table without locking. Each entry is two
+// 64-bit words: the data, packed, and the key XOR-ed with the data. Threads
+// may overwrite each other's entries halfway, but then the two words no
+// longer match the key when they are XOR-ed together, so a torn entry is
+// simply a miss.
+
+pub const DEFAULT_HASH_MB: usize = 16;
+pub const MAX_HASH_MB: usize = 4096;
+const ENTRIES_PER_BUCKET: usize = 4;
+const MEGABYTE: usize = 1024 * 1024;
+
+// Layout of the data word: the move (without its sort score), the score
+// offset to be positive, the depth, the bound and the age.
+const MOVE_BITS: u64 = 24;
+const SCORE_SHIFT: u64 = MOVE_BITS;
+const SCORE_BITS: u64 = 18;
+const SCORE_OFFSET: i32 = 1 << (SCORE_BITS - 1);
+const DEPTH_SHIFT: u64 = SCORE_SHIFT + SCORE_BITS;
+const BOUND_SHIFT: u64 = DEPTH_SHIFT + 8;
+const AGE_SHIFT: u64 = BOUND_SHIFT + 2;
+
+// What the stored score means with regard to the window it was searched with.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Bound {
+    None,
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Copy, Clone)]
+pub struct TTEntry {
+    best_move: u32,
+    pub score: i32,
+    pub depth: i8,
+    pub bound: Bound,
+    age: u8,
+}
+
+impl TTEntry {
+    // The move stored in this entry, if any.
+    pub fn best_move(&self) -> Option<Move> {
+        if self.best_move == 0 {
+            None
+        } else {
+            Some(Move::new(self.best_move as usize))
+        }
+    }
+
+    // An empty entry packs to zero.
+    fn pack(&self) -> u64 {
+        if self.bound == Bound::None {
+            return 0;
+        }
+        self.best_move as u64
+            | ((self.score + SCORE_OFFSET) as u64) << SCORE_SHIFT
+            | (self.depth as u8 as u64) << DEPTH_SHIFT
+            | (self.bound as u64) << BOUND_SHIFT
+            | (self.age as u64) << AGE_SHIFT
+    }
+
+    fn unpack(data: u64) -> Self {
+        let field = |shift: u64, bits: u64| (data >> shift) & ((1 << bits) - 1);
+        Self {
+            best_move: field(0, MOVE_BITS) as u32,
+            score: field(SCORE_SHIFT, SCORE_BITS) as i32 - SCORE_OFFSET,
+            depth: field(DEPTH_SHIFT, 8) as u8 as i8,
+            bound: match field(BOUND_SHIFT, 2) {
+                1 => Bound::Exact,
+                2 => Bound::Lower,
+                3 => Bound::Upper,
+                _ => Bound::None,
+            },
+            age: field(AGE_SHIFT, 8) as u8,
+        }
+    }
+}
+
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    // The entry, if the slot holds the position with this key.
+    fn load(&self, key: u64) -> Option<TTEntry> {
+        let data = self.data.load(Ordering::Relaxed);
+        let stored_key = self.key.load(Ordering::Relaxed) ^ data;
+        (data != 0 && stored_key == key).then(|| TTEntry::unpack(data))
+    }
+
+    fn save(&self, key: u64, entry: &TTEntry) {
+        let data = entry.pack();
+        self.key.store(key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.key.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+struct Bucket {
+    slots: [Slot; ENTRIES_PER_BUCKET],
+}
+
+impl Bucket {
+    fn new() -> Self {
+        Self {
+            slots: std::array::from_fn(|_| Slot {
+                key: AtomicU64::new(0),
+                data: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: u8,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> Self {
+        let mut tt = Self {
+            buckets: Vec::new(),
+            age: 0,
+        };
+        tt.resize(megabytes);
+        tt
+    }
+
+    // Reallocate the table. The number of buckets is the largest power of
+    // two that fits in the requested amount of memory. All entries are lost.
+    pub fn resize(&mut self, megabytes: usize) {
+        let bytes = megabytes.clamp(1, MAX_HASH_MB) * MEGABYTE;
+        let max_buckets = bytes / std::mem::size_of::<Bucket>();
+        let nr_of_buckets = if max_buckets.is_power_of_two() {
+            max_buckets
+        } else {
+            max_buckets.next_power_of_two() / 2
+        };
+
+        self.buckets = (0..nr_of_buckets).map(|_| Bucket::new()).collect();
+        self.age = 0;
+    }
+
+    // Wipe all entries, for example when a new game starts.
+    pub fn clear(&mut self) {
+        self.buckets
+            .iter()
+            .for_each(|b| b.slots.iter().for_each(Slot::clear));
+        self.age = 0;
+    }
+
+    // Called at the start of each search. Entries from earlier searches are
+    // then preferred for replacement over entries from the current one.
+    pub fn new_search(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
+
+    // Look up the position. Mate scores are stored relative to the position
+    // itself, so they are converted back to be relative to the root.
+    pub fn probe(&self, key: u64, ply: usize) -> Option<TTEntry> {
+        self.buckets[self.index(key)]
+            .slots
+            .iter()
+            .find_map(|slot| slot.load(key))
+            .map(|e| TTEntry {
+                score: score_from_tt(e.score, ply),
+                ..e
+            })
+    }
+
+    // Store a search result. If the position is already in the bucket, that
+    // entry is overwritten; otherwise the least valuable entry is replaced:
+    // an entry from an earlier search, or else the one with the lowest depth.
+    pub fn store(
+        &self,
+        key: u64,
+        depth: i8,
+        score: i32,
+        bound: Bound,
+        best_move: Option<Move>,
+        ply: usize,
+    ) {
+        let bucket = &self.buckets[self.index(key)];
+
+        let (slot, old) = match bucket
+            .slots
+            .iter()
+            .find_map(|slot| slot.load(key).map(|e| (slot, Some(e))))
+        {
+            Some(found) => found,
+            None => {
+                let mut victim = &bucket.slots[0];
+                let mut lowest = i32::MAX;
+                for slot in &bucket.slots {
+                    let e = TTEntry::unpack(slot.data.load(Ordering::Relaxed));
+                    let worth = if e.bound == Bound::None {
+                        i32::MIN
+                    } else if e.age != self.age {
+                        e.depth as i32 - 256
+                    } else {
+                        e.depth as i32
+                    };
+                    if worth < lowest {
+                        lowest = worth;
+                        victim = slot;
+                    }
+                }
+                (victim, None)
+            }
+        };
+
+        // Keep the old move if this search didn't produce one for the
+        // same position.
+        let best_move = match (best_move, old) {
+            (Some(m), _) => m.get_move(),
+            (None, Some(old)) => old.best_move,
+            (None, None) => 0,
+        };
+
+        slot.save(
+            key,
+            &TTEntry {
+                best_move,
+                score: score_to_tt(score, ply),
+                depth,
+                bound,
+                age: self.age,
+            },
+        );
+    }
+
+    // Permille of the table that is in use by the current search. Only the
+    // first thousand buckets are sampled.
+    pub fn hash_full(&self) -> usize {
+        let sample = self.buckets.len().min(1000);
+        let used: usize = self.buckets[..sample]
+            .iter()
+            .map(|b| {
+                b.slots
+                    .iter()
+                    .map(|slot| TTEntry::unpack(slot.data.load(Ordering::Relaxed)))
+                    .filter(|e| e.bound != Bound::None && e.age == self.age)
+                    .count()
+            })
+            .sum();
+        used * 1000 / (sample * ENTRIES_PER_BUCKET)
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key as usize) & (self.buckets.len() - 1)
+    }
+}
+
+// Mate scores are stored as "mate in N from this position" instead of "mate
+// in N from the root", so they stay valid when the position is reached at a
+// different ply.
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE_BOUND {
+        score + ply as i32
+    } else if score < -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE_BOUND {
+        score - ply as i32
+    } else if score < -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
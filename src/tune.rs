@@ -0,0 +1,253 @@
+mod params;
+mod trace;
+
+use crate::board::Board;
+use crate::movegen::MoveGenerator;
+use params::Params;
+use std::thread;
+use trace::Tracer;
+
+// Texel tuning of the handcrafted evaluation.
+//
+// Run as "rust-chess tune <data file> [epochs] [output file]". The data
+// file holds quiet positions labelled with the result of the game they
+// were taken from, one per line, as "<fen>;<result>" or "<fen> [<result>]".
+// The result is from white's point of view: 1-0, 0-1 or 1/2-1/2, or 1.0,
+// 0.0 or 0.5.
+//
+// The evaluation of a position is mapped to an expected result with a
+// sigmoid, and the tuner minimizes the mean squared difference with the
+// actual results. First, the sigmoid's scaling constant K is fitted to the
+// current evaluation; then all parameters are optimized together by
+// gradient descent with Adam, starting from the current values. Every
+// REPORT_INTERVAL epochs, the parameters are written to the output file as
+// Rust constants, to be pasted into the evaluation modules.
+
+const DEFAULT_EPOCHS: usize = 5000;
+const DEFAULT_OUTPUT: &str = "tuned.rs";
+const REPORT_INTERVAL: usize = 100;
+
+// Adam settings. The parameters are in centipawns; the learning rate is
+// roughly the largest change of a parameter per epoch.
+const LEARNING_RATE: f64 = 1.0;
+const BETA1: f64 = 0.9;
+const BETA2: f64 = 0.999;
+const EPSILON: f64 = 1e-8;
+
+struct Position {
+    coefficients: Vec<(u32, f32)>,
+    result: f64,
+}
+
+pub fn run(args: &[String]) {
+    let Some(path) = args.first() else {
+        eprintln!("Usage: rust-chess tune <data file> [epochs] [output file]");
+        return;
+    };
+    let epochs = match args.get(1).map(|epochs| epochs.parse::<usize>()) {
+        None => DEFAULT_EPOCHS,
+        Some(Ok(epochs)) => epochs,
+        Some(Err(_)) => {
+            eprintln!("Invalid number of epochs: {}", args[1]);
+            return;
+        }
+    };
+    let output = args.get(2).map_or(DEFAULT_OUTPUT, String::as_str);
+
+    let mut params = Params::current();
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Can't read '{}': {}", path, e);
+            return;
+        }
+    };
+    let lines: Vec<&str> = data.lines().filter(|l| !l.trim().is_empty()).collect();
+    let positions = load(&lines, &params);
+    if positions.is_empty() {
+        eprintln!("No positions in '{}'", path);
+        return;
+    }
+    println!(
+        "Loaded {} positions, skipped {}; tuning {} parameters",
+        positions.len(),
+        lines.len() - positions.len(),
+        params.len()
+    );
+
+    let k = find_k(&positions, &params.values);
+    println!(
+        "K = {:.4}, error {:.6}",
+        k,
+        error(&positions, &params.values, k)
+    );
+
+    let mut adam = Adam::new(params.len());
+    for epoch in 1..=epochs {
+        let gradient = gradient(&positions, &params.values, k);
+        adam.step(&mut params.values, &gradient);
+
+        if epoch % REPORT_INTERVAL == 0 || epoch == epochs {
+            let error = error(&positions, &params.values, k);
+            println!("Epoch {epoch}: error {error:.6}");
+            if let Err(e) = std::fs::write(output, params.to_rust()) {
+                eprintln!("Can't write '{}': {}", output, e);
+                return;
+            }
+        }
+    }
+
+    println!("Parameters written to {output}");
+}
+
+// Trace all positions that can be parsed.
+fn load(lines: &[&str], params: &Params) -> Vec<Position> {
+    in_parallel(lines, |lines| {
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+        let mut tracer = Tracer::new(params);
+
+        lines
+            .iter()
+            .filter_map(|line| {
+                let (fen, result) = parse_line(line)?;
+                board.fen_read(Some(fen)).ok()?;
+                Some(Position {
+                    coefficients: tracer.trace(&board, &mg, &params.index),
+                    result,
+                })
+            })
+            .collect::<Vec<Position>>()
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+fn parse_line(line: &str) -> Option<(&str, f64)> {
+    let (fen, result) = match line.rsplit_once(';') {
+        Some(split) => split,
+        None => line.rsplit_once('[')?,
+    };
+    let result = match result.trim().trim_matches(['"', ']']) {
+        "1-0" | "1.0" | "1" => 1.0,
+        "0-1" | "0.0" | "0" => 0.0,
+        "1/2-1/2" | "0.5" => 0.5,
+        _ => return None,
+    };
+    Some((fen.trim(), result))
+}
+
+fn evaluate(position: &Position, values: &[f64]) -> f64 {
+    position
+        .coefficients
+        .iter()
+        .map(|&(i, coefficient)| coefficient as f64 * values[i as usize])
+        .sum()
+}
+
+// The expected result for an evaluation.
+fn sigmoid(eval: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+// The mean squared error of the expected results.
+fn error(positions: &[Position], values: &[f64], k: f64) -> f64 {
+    let sum: f64 = in_parallel(positions, |positions| {
+        positions
+            .iter()
+            .map(|p| (p.result - sigmoid(evaluate(p, values), k)).powi(2))
+            .sum::<f64>()
+    })
+    .iter()
+    .sum();
+    sum / positions.len() as f64
+}
+
+// The gradient of the error with respect to each parameter.
+fn gradient(positions: &[Position], values: &[f64], k: f64) -> Vec<f64> {
+    let parts = in_parallel(positions, |positions| {
+        let mut gradient = vec![0.0; values.len()];
+        for p in positions {
+            let s = sigmoid(evaluate(p, values), k);
+            let slope = (s - p.result) * s * (1.0 - s);
+            for &(i, coefficient) in &p.coefficients {
+                gradient[i as usize] += slope * coefficient as f64;
+            }
+        }
+        gradient
+    });
+
+    let scale = 2.0 * k * 10f64.ln() / 400.0 / positions.len() as f64;
+    let mut gradient = vec![0.0; values.len()];
+    for part in parts {
+        for (g, p) in gradient.iter_mut().zip(part) {
+            *g += p * scale;
+        }
+    }
+    gradient
+}
+
+// Find the K with the lowest error by ternary search; the error is convex
+// in K.
+fn find_k(positions: &[Position], values: &[f64]) -> f64 {
+    let (mut low, mut high) = (0.0, 10.0);
+    for _ in 0..100 {
+        let a = low + (high - low) / 3.0;
+        let b = high - (high - low) / 3.0;
+        if error(positions, values, a) < error(positions, values, b) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+    (low + high) / 2.0
+}
+
+struct Adam {
+    m: Vec<f64>,
+    v: Vec<f64>,
+    t: i32,
+}
+
+impl Adam {
+    fn new(size: usize) -> Self {
+        Self {
+            m: vec![0.0; size],
+            v: vec![0.0; size],
+            t: 0,
+        }
+    }
+
+    fn step(&mut self, values: &mut [f64], gradient: &[f64]) {
+        self.t += 1;
+        let m_correction = 1.0 - BETA1.powi(self.t);
+        let v_correction = 1.0 - BETA2.powi(self.t);
+
+        for (i, &g) in gradient.iter().enumerate() {
+            self.m[i] = BETA1 * self.m[i] + (1.0 - BETA1) * g;
+            self.v[i] = BETA2 * self.v[i] + (1.0 - BETA2) * g * g;
+            let m = self.m[i] / m_correction;
+            let v = self.v[i] / v_correction;
+            values[i] -= LEARNING_RATE * m / (v.sqrt() + EPSILON);
+        }
+    }
+}
+
+// Split the items over all CPU cores and run "f" on each part.
+fn in_parallel<I: Sync, T: Send>(items: &[I], f: impl Fn(&[I]) -> T + Sync) -> Vec<T> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = items.len().div_ceil(threads).max(1);
+    let f = &f;
+
+    thread::scope(|s| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move || f(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("Tuning thread panicked"))
+            .collect()
+    })
+}
@@ -0,0 +1,251 @@
+use crate::defs::NrOf;
+use crate::evaluate::{king_safety, mobility, pawns, psqt};
+use std::fmt::Write;
+
+// The evaluation parameters as one vector of numbers, the way the tuner
+// sees them. Each group of parameters corresponds to one constant in the
+// evaluation; the groups are written back out as Rust source, in the same
+// form as the constants, so they can be pasted over the old ones.
+
+// Which part of the tapered evaluation a parameter belongs to. "Both" is
+// used for the terms that have a single value for the whole game.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Phase {
+    Mg,
+    Eg,
+    Both,
+}
+
+// How a group of parameters is written out.
+enum Shape {
+    Scalar,
+    Pair,
+    Array,
+    Table,
+}
+
+struct Group {
+    file: &'static str,
+    name: &'static str,
+    ty: &'static str,
+    shape: Shape,
+    offset: usize,
+    len: usize,
+}
+
+// The offset of each group in the parameter vector. Arrays and tables
+// take one entry per element; pairs take the middlegame value followed by
+// the endgame value.
+pub struct Index {
+    pub values_mg: usize,
+    pub values_eg: usize,
+    pub psqt_mg: [usize; NrOf::PIECE_TYPES],
+    pub psqt_eg: [usize; NrOf::PIECE_TYPES],
+    pub doubled: usize,
+    pub isolated: usize,
+    pub backward: usize,
+    pub connected: usize,
+    pub passed_mg: usize,
+    pub passed_eg: usize,
+    pub free_path: usize,
+    pub king_proximity: usize,
+    pub mobility_mg: usize,
+    pub mobility_eg: usize,
+    pub king_attack_weight: usize,
+    pub shield_close: usize,
+    pub shield_far: usize,
+    pub open_file: usize,
+    pub semi_open_file: usize,
+}
+
+pub struct Params {
+    groups: Vec<Group>,
+    pub values: Vec<f64>,
+    pub phases: Vec<Phase>,
+    pub index: Index,
+}
+
+impl Params {
+    // The parameters the evaluation currently uses.
+    pub fn current() -> Self {
+        use Phase::*;
+        use Shape::*;
+
+        let mut groups = Vec::new();
+        let mut values = Vec::new();
+        let mut phases = Vec::new();
+        let mut add = |file, name, ty, shape, group_values: &[i32], phase| {
+            let offset = values.len();
+            values.extend(group_values.iter().map(|&v| v as f64));
+            if let Pair = shape {
+                phases.extend([Mg, Eg]);
+            } else {
+                phases.extend(group_values.iter().map(|_| phase));
+            }
+            groups.push(Group {
+                file,
+                name,
+                ty,
+                shape,
+                offset,
+                len: group_values.len(),
+            });
+            offset
+        };
+
+        const PSQT: &str = "psqt.rs";
+        const PAWNS: &str = "pawns.rs";
+        const MOBILITY: &str = "mobility.rs";
+        const KING_SAFETY: &str = "king_safety.rs";
+        const PIECES: &str = "[i32; NrOf::PIECE_TYPES]";
+        const RANKS: &str = "[i32; NrOf::RANKS]";
+        const PAIR: &str = "(i32, i32)";
+        const I32: &str = "i32";
+
+        let values_mg = add(PSQT, "VALUES_MG", PIECES, Array, &psqt::VALUES_MG, Mg);
+        let values_eg = add(PSQT, "VALUES_EG", PIECES, Array, &psqt::VALUES_EG, Eg);
+        let mut psqt_mg = [0; NrOf::PIECE_TYPES];
+        let mut psqt_eg = [0; NrOf::PIECE_TYPES];
+        let tables = [
+            ("KING_MG", "KING_EG", psqt::KING_MG, psqt::KING_EG),
+            ("QUEEN_MG", "QUEEN_EG", psqt::QUEEN_MG, psqt::QUEEN_EG),
+            ("ROOK_MG", "ROOK_EG", psqt::ROOK_MG, psqt::ROOK_EG),
+            ("BISHOP_MG", "BISHOP_EG", psqt::BISHOP_MG, psqt::BISHOP_EG),
+            ("KNIGHT_MG", "KNIGHT_EG", psqt::KNIGHT_MG, psqt::KNIGHT_EG),
+            ("PAWN_MG", "PAWN_EG", psqt::PAWN_MG, psqt::PAWN_EG),
+        ];
+        for (piece, (name_mg, name_eg, mg, eg)) in tables.iter().enumerate() {
+            psqt_mg[piece] = add(PSQT, name_mg, "Psqt", Table, mg, Mg);
+            psqt_eg[piece] = add(PSQT, name_eg, "Psqt", Table, eg, Eg);
+        }
+
+        let pair = |(mg, eg): (i32, i32)| [mg, eg];
+        let index = Index {
+            values_mg,
+            values_eg,
+            psqt_mg,
+            psqt_eg,
+            doubled: add(PAWNS, "DOUBLED", PAIR, Pair, &pair(pawns::DOUBLED), Mg),
+            isolated: add(PAWNS, "ISOLATED", PAIR, Pair, &pair(pawns::ISOLATED), Mg),
+            backward: add(PAWNS, "BACKWARD", PAIR, Pair, &pair(pawns::BACKWARD), Mg),
+            connected: add(PAWNS, "CONNECTED", RANKS, Array, &pawns::CONNECTED, Both),
+            passed_mg: add(PAWNS, "PASSED_MG", RANKS, Array, &pawns::PASSED_MG, Mg),
+            passed_eg: add(PAWNS, "PASSED_EG", RANKS, Array, &pawns::PASSED_EG, Eg),
+            free_path: add(PAWNS, "FREE_PATH", RANKS, Array, &pawns::FREE_PATH, Eg),
+            king_proximity: add(
+                PAWNS,
+                "KING_PROXIMITY",
+                RANKS,
+                Array,
+                &pawns::KING_PROXIMITY,
+                Eg,
+            ),
+            mobility_mg: add(
+                MOBILITY,
+                "MOBILITY_MG",
+                PIECES,
+                Array,
+                &mobility::MOBILITY_MG,
+                Mg,
+            ),
+            mobility_eg: add(
+                MOBILITY,
+                "MOBILITY_EG",
+                PIECES,
+                Array,
+                &mobility::MOBILITY_EG,
+                Eg,
+            ),
+            king_attack_weight: add(
+                MOBILITY,
+                "KING_ATTACK_WEIGHT",
+                PIECES,
+                Array,
+                &mobility::KING_ATTACK_WEIGHT,
+                Mg,
+            ),
+            shield_close: add(
+                KING_SAFETY,
+                "SHIELD_CLOSE",
+                I32,
+                Scalar,
+                &[king_safety::SHIELD_CLOSE],
+                Mg,
+            ),
+            shield_far: add(
+                KING_SAFETY,
+                "SHIELD_FAR",
+                I32,
+                Scalar,
+                &[king_safety::SHIELD_FAR],
+                Mg,
+            ),
+            open_file: add(
+                KING_SAFETY,
+                "OPEN_FILE",
+                I32,
+                Scalar,
+                &[king_safety::OPEN_FILE],
+                Mg,
+            ),
+            semi_open_file: add(
+                KING_SAFETY,
+                "SEMI_OPEN_FILE",
+                I32,
+                Scalar,
+                &[king_safety::SEMI_OPEN_FILE],
+                Mg,
+            ),
+        };
+
+        Self {
+            groups,
+            values,
+            phases,
+            index,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    // The parameters as Rust constants, grouped by the file they belong in.
+    pub fn to_rust(&self) -> String {
+        let mut out = String::new();
+        let mut file = "";
+
+        for group in &self.groups {
+            if group.file != file {
+                file = group.file;
+                let _ = writeln!(out, "\n// src/evaluate/{file}\n");
+            }
+
+            let values: Vec<i32> = self.values[group.offset..group.offset + group.len]
+                .iter()
+                .map(|v| v.round() as i32)
+                .collect();
+            let list = |values: &[i32]| {
+                let strings: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                strings.join(", ")
+            };
+            let declaration = format!("pub const {}: {} =", group.name, group.ty);
+
+            let _ = match group.shape {
+                Shape::Scalar => writeln!(out, "{declaration} {};", values[0]),
+                Shape::Pair => writeln!(out, "{declaration} ({});", list(&values)),
+                Shape::Array => writeln!(out, "{declaration} [{}];", list(&values)),
+                Shape::Table => {
+                    let _ = writeln!(out, "#[rustfmt::skip]\n{declaration} [");
+                    for row in values.chunks(NrOf::FILES) {
+                        let cells: Vec<String> = row.iter().map(|v| format!("{v:3},")).collect();
+                        let _ = writeln!(out, "    {}", cells.join(" "));
+                    }
+                    writeln!(out, "];\n")
+                }
+            };
+        }
+
+        out
+    }
+}
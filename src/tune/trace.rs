@@ -0,0 +1,194 @@
+use super::params::{Index, Params, Phase};
+use crate::BitBoard;
+use crate::board::Board;
+use crate::defs::{NrOf, Side, Sides};
+use crate::evaluate::king_safety::{attacker_scale, classify_shelter};
+use crate::evaluate::mobility::{MOBILITY_AVERAGE, piece_mobility};
+use crate::evaluate::pawns::{classify_passed_pawn, classify_pawns};
+use crate::evaluate::psqt::{self, MAX_PHASE};
+use crate::movegen::MoveGenerator;
+
+// The handcrafted evaluation is linear in its parameters: it is a sum of
+// parameter * count terms (for example, the isolated pawn penalty times the
+// number of isolated white pawns, minus the number of black ones), blended
+// between middlegame and endgame by the game phase. A trace records those
+// counts, the coefficients, for one position. The evaluation of the
+// position with any parameter vector is then the dot product of the
+// coefficients and the parameters, which is what the tuner optimizes.
+//
+// The trace uses the same classification functions as the evaluation, so
+// the two agree. The evaluation rounds in a few places (tapering, the king
+// attack percentage); the trace doesn't.
+
+pub struct Tracer {
+    coefficients: Vec<f64>,
+    phases: Vec<Phase>,
+}
+
+impl Tracer {
+    pub fn new(params: &Params) -> Self {
+        Self {
+            coefficients: vec![0.0; params.len()],
+            phases: params.phases.clone(),
+        }
+    }
+
+    // The non-zero coefficients of the position, from white's point of
+    // view, with the game phase already applied.
+    pub fn trace(&mut self, board: &Board, mg: &MoveGenerator, index: &Index) -> Vec<(u32, f32)> {
+        for side in [Sides::WHITE, Sides::BLACK] {
+            self.psqt(board, side, index);
+            self.pawns(board, side, index);
+            self.shelter(board, side, index);
+        }
+        self.mobility(board, mg, index);
+
+        let phase = board.game_state.phase.clamp(0, MAX_PHASE) as f64 / MAX_PHASE as f64;
+        let mut trace = Vec::new();
+        for (i, coefficient) in self.coefficients.iter_mut().enumerate() {
+            if *coefficient != 0.0 {
+                let weight = match self.phases[i] {
+                    Phase::Mg => phase,
+                    Phase::Eg => 1.0 - phase,
+                    Phase::Both => 1.0,
+                };
+                trace.push((i as u32, (*coefficient * weight) as f32));
+                *coefficient = 0.0;
+            }
+        }
+
+        trace
+    }
+
+    fn add(&mut self, side: Side, parameter: usize, count: f64) {
+        let sign = if side == Sides::WHITE { 1.0 } else { -1.0 };
+        self.coefficients[parameter] += sign * count;
+    }
+
+    fn psqt(&mut self, board: &Board, side: Side, index: &Index) {
+        for piece in 0..NrOf::PIECE_TYPES {
+            let mut bb = board.pieces[side][piece];
+            while bb.0 > 0 {
+                let square = psqt::index(side, BitBoard::next(&mut bb).0);
+                self.add(side, index.values_mg + piece, 1.0);
+                self.add(side, index.values_eg + piece, 1.0);
+                self.add(side, index.psqt_mg[piece] + square, 1.0);
+                self.add(side, index.psqt_eg[piece] + square, 1.0);
+            }
+        }
+    }
+
+    fn pawns(&mut self, board: &Board, side: Side, index: &Index) {
+        let mut passed = Vec::new();
+        classify_pawns(board, side, |square, pawn| {
+            let rank = pawn.relative_rank;
+            for (present, parameter) in [
+                (pawn.doubled, index.doubled),
+                (pawn.isolated, index.isolated),
+                (pawn.backward, index.backward),
+            ] {
+                if present {
+                    self.add(side, parameter, 1.0);
+                    self.add(side, parameter + 1, 1.0);
+                }
+            }
+            if pawn.connected {
+                self.add(side, index.connected + rank, 1.0);
+            }
+            if pawn.passed {
+                self.add(side, index.passed_mg + rank, 1.0);
+                self.add(side, index.passed_eg + rank, 1.0);
+                passed.push(square);
+            }
+        });
+
+        for square in passed {
+            let pawn = classify_passed_pawn(board, side, square);
+            let rank = pawn.relative_rank;
+            if pawn.free_path {
+                self.add(side, index.free_path + rank, 1.0);
+            }
+            self.add(side, index.king_proximity + rank, pawn.king_distance as f64);
+        }
+    }
+
+    fn mobility(&mut self, board: &Board, mg: &MoveGenerator, index: &Index) {
+        for side in [Sides::WHITE, Sides::BLACK] {
+            let mut attackers = 0;
+            let mut zone_attacks = [0; NrOf::PIECE_TYPES];
+            piece_mobility(board, mg, side, |piece, squares, zone| {
+                let mobility = (squares - MOBILITY_AVERAGE[piece]) as f64;
+                self.add(side, index.mobility_mg + piece, mobility);
+                self.add(side, index.mobility_eg + piece, mobility);
+                if zone > 0 {
+                    attackers += 1;
+                    zone_attacks[piece] += zone;
+                }
+            });
+
+            // An attack on the enemy king counts for the attacking side.
+            let scale = attacker_scale(attackers) as f64 / 100.0;
+            for (piece, &zone) in zone_attacks.iter().enumerate() {
+                self.add(side, index.king_attack_weight + piece, zone as f64 * scale);
+            }
+        }
+    }
+
+    fn shelter(&mut self, board: &Board, side: Side, index: &Index) {
+        let shelter = classify_shelter(board, side);
+        self.add(side, index.shield_close, shelter.close as f64);
+        self.add(side, index.shield_far, shelter.far as f64);
+        self.add(side, index.open_file, shelter.open as f64);
+        self.add(side, index.semi_open_file, shelter.semi_open as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tracer;
+    use crate::board::Board;
+    use crate::defs::Sides;
+    use crate::evaluate::evaluate_position;
+    use crate::evaluate::pawns::PawnHashTable;
+    use crate::movegen::MoveGenerator;
+    use crate::tune::params::Params;
+
+    const FENS: [&str; 6] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "6k1/5ppp/8/3P4/8/8/5PPP/3R2K1 b - - 0 1",
+        "2kr3r/ppp2ppp/2n5/2b1p3/4P1b1/2NP1N2/PPP2PPP/R1B1KB1R b KQ - 3 9",
+    ];
+
+    // The trace with the current parameters gives the evaluation, except
+    // for rounding.
+    #[test]
+    fn trace_matches_evaluation() {
+        let mg = MoveGenerator::new();
+        let params = Params::current();
+        let mut tracer = Tracer::new(&params);
+        let mut pawn_table = PawnHashTable::new();
+
+        for fen in FENS {
+            let mut board = Board::new();
+            board.fen_read(Some(fen)).unwrap();
+
+            let traced: f64 = tracer
+                .trace(&board, &mg, &params.index)
+                .iter()
+                .map(|&(i, coefficient)| coefficient as f64 * params.values[i as usize])
+                .sum();
+            let mut eval = evaluate_position(&mut board, &mg, &mut pawn_table);
+            if board.game_state.side_to_move == Sides::BLACK {
+                eval = -eval;
+            }
+
+            assert!(
+                (traced - eval as f64).abs() <= 3.0,
+                "{fen}: {traced} vs {eval}"
+            );
+        }
+    }
+}
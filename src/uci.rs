@@ -1,9 +1,36 @@
 use crate::board::Board;
-use crate::defs::{FEN_START_POSITION, MAX_DEPTH};
+use crate::defs::{FEN_START_POSITION, MAX_DEPTH, Sides};
+use crate::evaluate::nnue::Network;
+use crate::evaluate::nnue::simd::{self, Level};
+use crate::movegen::bit_move::Move;
 use crate::movegen::{MoveGenerator, uci};
 use crate::search;
+use crate::search::transposition::{DEFAULT_HASH_MB, TranspositionTable};
+use crate::search::{SearchOptions, SearchSignals};
+use std::io::Write;
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::thread::{self, JoinHandle};
+
+mod command;
+mod options;
+
+use command::{GoParams, UciCommand};
+use options::{OptionValue, UciOption};
+
+// A search running in its own thread, and the signals to control it.
+struct RunningSearch {
+    handle: JoinHandle<()>,
+    signals: Arc<SearchSignals>,
+}
+
+impl RunningSearch {
+    // Tell the search to stop, and wait until it has sent "bestmove".
+    fn stop(self) {
+        self.signals.stop.store(true, Ordering::Relaxed);
+        let _ = self.handle.join();
+    }
+}
 
 pub fn message_loop() {
     let board = Arc::new(Mutex::new(Board::new()));
@@ -13,102 +40,119 @@ pub fn message_loop() {
         let _ = b.fen_read(None);
     }
 
+    let tt = Arc::new(Mutex::new(TranspositionTable::new(DEFAULT_HASH_MB)));
+    let move_generator = Arc::new(MoveGenerator::new());
+
     // Spawn a thread to listen for UCI commands
     let board_clone = Arc::clone(&board);
     let input_thread = thread::spawn(move || {
+        let mut running: Option<RunningSearch> = None;
+        let mut settings = Settings::new();
         loop {
+            // Forget about a search that has already finished by itself.
+            if running.as_ref().is_some_and(|r| r.handle.is_finished()) {
+                running = None;
+            }
+
+            // The end of the input means the GUI has gone; shut down as if
+            // it had sent "quit".
             let mut input = String::new();
-            std::io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read line");
-            let command = input.trim();
-
-            let options = command.split_whitespace().collect::<Vec<&str>>();
-            match options.as_slice() {
-                ["uci"] => {
-                    uci();
+            let command = match std::io::stdin().read_line(&mut input) {
+                Ok(0) | Err(_) => UciCommand::Quit,
+                Ok(_) if input.trim().is_empty() => continue,
+                Ok(_) => match command::parse(&input) {
+                    Ok(command) => command,
+                    Err(e) => {
+                        info_string(&e);
+                        continue;
+                    }
+                },
+            };
+
+            match command {
+                UciCommand::Uci => uci(),
+                UciCommand::IsReady => {
+                    println!("readyok");
+                    let _ = std::io::stdout().flush();
                 }
-                ["ucinewgame"] => {
+                UciCommand::UciNewGame => {
+                    // The search thread holds the table until it is done.
+                    if let Some(search) = running.take() {
+                        search.stop();
+                    }
                     let mut b = board_clone.lock().unwrap();
-                    reset(&mut *b);
+                    reset(&mut b);
+                    tt.lock().unwrap().clear();
                 }
-                ["isready"] => println!("readyok"),
-                ["position", pos_options @ ..] => {
-                    let mut b = board_clone.lock().unwrap();
-                    position(&mut *b, pos_options);
+                UciCommand::SetOption { name, value } => {
+                    let searching = running.is_some();
+                    let result = options::parse_setoption(&name, value.as_deref()).and_then(
+                        |(option, value)| set_option(&mut settings, &tt, searching, option, value),
+                    );
+                    if let Err(e) = result {
+                        info_string(&e);
+                    }
                 }
-                ["go", go_options @ ..] => {
-                    // Parse search options and capture the side-to-move under a short lock.
-                    // We deliberately do NOT hold the lock during the whole search so the
-                    // input thread can still receive commands (eg. "quit") while searching.
-                    let side_to_move = {
-                        let b = board_clone.lock().unwrap();
-                        b.game_state.side_to_move
-                    };
-
-                    let mut info = search::SearchInfo::new();
-                    let mut i = 0;
-                    while i < go_options.len() {
-                        match go_options[i] {
-                            "infinite" => {
-                                // Implement infinite search if needed
-                            }
-                            "wtime" => {
-                                if i + 1 < go_options.len() && side_to_move == 0 {
-                                    info.time = go_options[i + 1].parse().unwrap_or(0);
-                                    i += 1;
-                                }
-                            }
-                            "btime" => {
-                                if i + 1 < go_options.len() && side_to_move == 1 {
-                                    info.time = go_options[i + 1].parse().unwrap_or(0);
-                                    i += 1;
-                                }
-                            }
-                            "winc" => {
-                                if i + 1 < go_options.len() && side_to_move == 0 {
-                                    info.increment = go_options[i + 1].parse().unwrap_or(0);
-                                    i += 1;
-                                }
-                            }
-                            "binc" => {
-                                if i + 1 < go_options.len() && side_to_move == 1 {
-                                    info.increment = go_options[i + 1].parse().unwrap_or(0);
-                                    i += 1;
-                                }
-                            }
-                            "depth" => {
-                                if i + 1 < go_options.len() {
-                                    info.depth = go_options[i + 1].parse().unwrap_or(0);
-                                    i += 1;
-                                }
-                            }
-                            _ => {}
-                        }
-                        i += 1;
+                UciCommand::Position { fen, moves } => {
+                    let mut b = board_clone.lock().unwrap();
+                    if let Err(e) = position(&mut b, &move_generator, fen.as_deref(), &moves) {
+                        info_string(&e);
                     }
-
-                    // If no depth was specified, set a default depth
-                    if info.depth <= 0 {
-                        info.depth = MAX_DEPTH;
+                }
+                UciCommand::Go(params) => {
+                    // Only one search can run at a time.
+                    if let Some(search) = running.take() {
+                        search.stop();
                     }
 
-                    // Spawn a dedicated search thread. It will lock the board for the
-                    // duration of the search. The input thread remains free to handle
-                    // commands such as "quit" (which calls process::exit and terminates
-                    // the whole process immediately).
-                    let board_for_search = Arc::clone(&board_clone);
-                    thread::spawn(move || {
-                        let mut b = board_for_search.lock().unwrap();
-                        let move_generator = MoveGenerator::new();
-                        let _ = search::search_position(&mut *b, &info, &move_generator);
+                    // Spawn a dedicated search thread, which searches its own copy
+                    // of the board. The input thread remains free to handle commands
+                    // such as "stop", "ponderhit" and "isready" during the search.
+                    // Without a network, the handcrafted evaluation is used.
+                    let mut board_for_search = board_clone.lock().unwrap().clone();
+                    let info = search_info(&params, &mut board_for_search, &move_generator);
+                    board_for_search.set_network(if settings.use_nnue {
+                        settings.network.clone()
+                    } else {
+                        None
+                    });
+                    let tt_for_search = Arc::clone(&tt);
+                    let mg_for_search = Arc::clone(&move_generator);
+                    let signals = Arc::new(SearchSignals::new(info.ponder));
+                    let signals_for_search = Arc::clone(&signals);
+                    let options_for_search = settings.search;
+                    let threads = settings.threads;
+                    let handle = thread::spawn(move || {
+                        let mut t = tt_for_search.lock().unwrap();
+                        let result = search::search_position(
+                            &mut board_for_search,
+                            &info,
+                            &options_for_search,
+                            &mg_for_search,
+                            &mut t,
+                            &signals_for_search,
+                            threads,
+                        );
+                        bestmove(&result);
                     });
+                    running = Some(RunningSearch { handle, signals });
                 }
-                ["quit"] => {
-                    std::process::exit(0);
+                UciCommand::Stop => {
+                    if let Some(search) = running.take() {
+                        search.stop();
+                    }
+                }
+                UciCommand::PonderHit => {
+                    if let Some(search) = &running {
+                        search.signals.ponder.store(false, Ordering::Relaxed);
+                    }
+                }
+                UciCommand::Quit => {
+                    if let Some(search) = running.take() {
+                        search.stop();
+                    }
+                    break;
                 }
-
-                _ => eprintln!("Unknown command: '{}'", command.trim_end()),
             }
         }
     });
@@ -120,114 +164,171 @@ pub fn message_loop() {
 fn uci() {
     println!("id name Newton {}", env!("CARGO_PKG_VERSION"));
     println!("id author Luskidoo");
+    for option in &options::OPTIONS {
+        println!("{}", option);
+    }
     println!("uciok");
 }
 
-fn position(board: &mut Board, options: &[&str]) {
-    // The `options` slice contains everything after the word "position".
-    // Valid forms:
-    //  - position startpos
-    //  - position startpos moves e2e4 e7e5 ...
-    //  - position fen <fen string> [moves ...]
-    if options.is_empty() {
-        return;
-    }
+// The engine settings that can be changed with "setoption", apart from the
+// hash size, which belongs to the transposition table.
+struct Settings {
+    search: SearchOptions,
+    threads: usize,
+    network: Option<Arc<Network>>,
+    use_nnue: bool,
+}
 
-    match options[0] {
-        "startpos" => {
-            let _ = board.fen_read(Some(FEN_START_POSITION));
-            // Find the "moves" token and apply any moves after it
-            if let Some(moves_idx) = options.iter().position(|&x| x == "moves") {
-                let moves = &options[moves_idx + 1..];
-                apply_moves(board, moves);
-            }
+impl Settings {
+    fn new() -> Self {
+        Self {
+            search: SearchOptions::new(),
+            threads: search::DEFAULT_THREADS,
+            network: None,
+            use_nnue: false,
         }
+    }
+}
 
-        "fen" => {
-            // The FEN itself can contain spaces; consume tokens until we hit
-            // an optional "moves" token (which begins the move list).
-            let rest = &options[1..];
-            let mut fen_end = rest.len();
-            for (i, &tok) in rest.iter().enumerate() {
-                if tok == "moves" {
-                    fen_end = i;
-                    break;
-                }
+// Apply an option value that was already checked against the registry.
+// A running search holds the transposition table, so the table can't be
+// changed until it is done.
+fn set_option(
+    settings: &mut Settings,
+    tt: &Mutex<TranspositionTable>,
+    searching: bool,
+    option: &UciOption,
+    value: OptionValue,
+) -> Result<(), String> {
+    let search = &mut settings.search;
+    match (option.name, value) {
+        ("Hash" | "Clear Hash", _) if searching => {
+            return Err(format!("Can't change {} during a search", option.name));
+        }
+        ("Hash", OptionValue::Spin(mb)) => tt.lock().unwrap().resize(mb as usize),
+        ("Clear Hash", _) => tt.lock().unwrap().clear(),
+        ("Threads", OptionValue::Spin(n)) => settings.threads = n as usize,
+        ("MultiPV", OptionValue::Spin(n)) => search.multi_pv = n as usize,
+        // Only tells the engine that the GUI may ask it to ponder.
+        ("Ponder", _) => (),
+        ("EvalFile", OptionValue::String(path)) => settings.network = Some(Network::load(&path)?),
+        ("UseNNUE", OptionValue::Check(on)) => {
+            settings.use_nnue = on;
+            if on && settings.network.is_none() {
+                info_string("No EvalFile loaded; using the handcrafted evaluation");
             }
+        }
+        ("SIMD", OptionValue::Combo(var)) => simd::set_limit(match var {
+            "SSE4.1" => Level::Sse41,
+            "AVX2" => Level::Avx2,
+            "Scalar" => Level::Scalar,
+            _ => Level::Avx512,
+        }),
+        ("LateMoveReductions", OptionValue::Check(on)) => search.late_move_reductions = on,
+        ("ReverseFutilityPruning", OptionValue::Check(on)) => search.reverse_futility_pruning = on,
+        ("FutilityPruning", OptionValue::Check(on)) => search.futility_pruning = on,
+        ("LateMovePruning", OptionValue::Check(on)) => search.late_move_pruning = on,
+        ("CheckExtensions", OptionValue::Check(on)) => search.check_extensions = on,
+        (name, _) => return Err(format!("Option {} is not supported", name)),
+    }
+    Ok(())
+}
 
-            // Join fen parts with spaces to reconstruct the full FEN string.
-            let fen_str = rest[..fen_end].join(" ");
-            let _ = board.fen_read(Some(&fen_str));
+// Diagnostics for the GUI. Unlike stderr, they show up in its engine log.
+fn info_string(message: &str) {
+    println!("info string {}", message);
+    let _ = std::io::stdout().flush();
+}
 
-            // If there are moves after the FEN, apply them
-            if fen_end < rest.len() && rest[fen_end] == "moves" {
-                let moves = &rest[fen_end + 1..];
-                apply_moves(board, moves);
-            }
-        }
+// The search limits for "go". Only the clock of the side to move matters.
+// Moves in "searchmoves" that are not legal are reported and left out.
+fn search_info(params: &GoParams, board: &mut Board, mg: &MoveGenerator) -> search::SearchInfo {
+    let mut info = search::SearchInfo::new();
+    let (time, increment) = match board.game_state.side_to_move {
+        Sides::WHITE => (params.wtime, params.winc),
+        _ => (params.btime, params.binc),
+    };
 
-        _ => {
-            eprintln!("Unknown position command: {:?}", options);
+    info.time = time.map_or(0, parse_time);
+    info.increment = increment.unwrap_or(0);
+    info.moves_to_go = params.moves_to_go;
+    info.move_time = params.move_time.map(parse_time);
+    info.nodes = params.nodes;
+    info.mate = params.mate;
+    info.infinite = params.infinite;
+    info.ponder = params.ponder;
+    // "depth 0" asks for the least search possible, which is one ply: the
+    // engine still has to find a move.
+    info.depth = params
+        .depth
+        .map_or(MAX_DEPTH, |depth| depth.clamp(1, MAX_DEPTH));
+    for move_str in &params.search_moves {
+        match legal_move(board, mg, move_str) {
+            Some(m) => info.search_moves.push(m),
+            None => info_string(&format!("Illegal move in searchmoves: '{}'", move_str)),
         }
     }
-    board.print_board();
+
+    info
 }
 
-fn apply_moves(board: &mut Board, moves: &[&str]) {
-    let mg = MoveGenerator::new(); // Create once for all moves
-    for &move_str in moves {
-        if let Some(m) = uci::parse_uci_move(board, move_str) {
-            let _ = board.make(m, &mg);
+// Find the legal move in the position that matches a move in UCI notation.
+// The parsed move doesn't have the castling, en passant and double step
+// flags, so it is looked up in the generated moves.
+fn legal_move(board: &mut Board, mg: &MoveGenerator, move_str: &str) -> Option<Move> {
+    let parsed = uci::parse_uci_move(board, move_str)?;
+    board.legal_moves(mg).into_iter().find(|m| {
+        m.from() == parsed.from() && m.to() == parsed.to() && m.promoted() == parsed.promoted()
+    })
+}
+
+// Some GUIs send a negative time when the clock has (almost) run out. Any
+// time that is given is at least 1 ms, so the engine still moves at once.
+fn parse_time(value: i64) -> u64 {
+    value.max(1) as u64
+}
+
+// Report the result of a search to the GUI. If there is no move at all
+// (checkmate or stalemate at the root), UCI expects the null move "0000".
+fn bestmove(result: &search::SearchResult) {
+    match (result.best_move, result.ponder_move) {
+        (Some(best), Some(ponder)) => {
+            println!(
+                "bestmove {} ponder {}",
+                best.as_string(),
+                ponder.as_string()
+            )
         }
+        (Some(best), None) => println!("bestmove {}", best.as_string()),
+        (None, _) => println!("bestmove 0000"),
     }
+    let _ = std::io::stdout().flush();
 }
 
-fn go(board: &mut Board, options: &[&str]) {
-    let mut info = search::SearchInfo::new();
+// Set up the position, from the initial position or a FEN, and play the
+// moves. On an invalid FEN, the board is left as it was; on an illegal move,
+// the moves before it have been played.
+fn position(
+    board: &mut Board,
+    mg: &MoveGenerator,
+    fen: Option<&str>,
+    moves: &[String],
+) -> Result<(), String> {
+    let fen = fen.unwrap_or(FEN_START_POSITION);
+    board.fen_read(Some(fen)).map_err(|part| match part {
+        0 => format!("Invalid FEN (wrong number of parts): '{}'", fen),
+        _ => format!("Invalid FEN (part {}): '{}'", part, fen),
+    })?;
 
-    let mut i = 0;
-    while i < options.len() {
-        match options[i] {
-            "infinite" => {
-                // Implement infinite search if needed
-                // todo!()
-            }
-            "wtime" => {
-                if i + 1 < options.len() && board.game_state.side_to_move == 0 {
-                    info.time = options[i + 1].parse().unwrap_or(0);
-                    i += 1;
-                }
-            }
-            "btime" => {
-                if i + 1 < options.len() && board.game_state.side_to_move == 1 {
-                    info.time = options[i + 1].parse().unwrap_or(0);
-                    i += 1;
-                }
-            }
-            "winc" => {
-                if i + 1 < options.len() && board.game_state.side_to_move == 0 {
-                    info.increment = options[i + 1].parse().unwrap_or(0);
-                    i += 1;
-                }
-            }
-            "binc" => {
-                if i + 1 < options.len() && board.game_state.side_to_move == 1 {
-                    info.increment = options[i + 1].parse().unwrap_or(0);
-                    i += 1;
-                }
-            }
-            "depth" => {
-                if i + 1 < options.len() {
-                    info.depth = options[i + 1].parse().unwrap_or(0);
-                    let move_generator = MoveGenerator::new();
-                    let _ = search::search_position(board, &info, &move_generator);
-                    i += 1;
-                }
+    for move_str in moves {
+        match legal_move(board, mg, move_str) {
+            Some(m) => {
+                board.make(m, mg);
             }
-            _ => {}
+            None => return Err(format!("Illegal move: '{}'", move_str)),
         }
-        i += 1;
     }
+    Ok(())
 }
 
 fn reset(board: &mut Board) {
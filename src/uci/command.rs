@@ -0,0 +1,370 @@
+// The commands the GUI sends, parsed from a line of input. Parsing only
+// looks at the text: whether a FEN, a move or an option value makes sense
+// for the engine is checked when the command is executed.
+
+#[derive(Debug, PartialEq)]
+pub enum UciCommand {
+    Uci,
+    IsReady,
+    UciNewGame,
+    SetOption {
+        name: String,
+        value: Option<String>,
+    },
+    // Without a FEN, the position starts from the initial position.
+    Position {
+        fen: Option<String>,
+        moves: Vec<String>,
+    },
+    Go(GoParams),
+    Stop,
+    PonderHit,
+    Quit,
+}
+
+// The arguments of "go". Times are in milliseconds; some GUIs send a
+// negative time when the clock has (almost) run out.
+#[derive(Debug, Default, PartialEq)]
+pub struct GoParams {
+    pub wtime: Option<i64>,
+    pub btime: Option<i64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub moves_to_go: Option<u64>,
+    pub depth: Option<i8>,
+    pub nodes: Option<u64>,
+    pub mate: Option<u32>,
+    pub move_time: Option<i64>,
+    pub infinite: bool,
+    pub ponder: bool,
+    pub search_moves: Vec<String>,
+}
+
+// The options of the "go" command.
+const GO_OPTIONS: [&str; 12] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+];
+
+pub fn parse(line: &str) -> Result<UciCommand, String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    match tokens.as_slice() {
+        ["uci"] => Ok(UciCommand::Uci),
+        ["isready"] => Ok(UciCommand::IsReady),
+        ["ucinewgame"] => Ok(UciCommand::UciNewGame),
+        ["setoption", args @ ..] => parse_setoption(args),
+        ["position", args @ ..] => parse_position(args),
+        ["go", args @ ..] => parse_go(args).map(UciCommand::Go),
+        ["stop"] => Ok(UciCommand::Stop),
+        ["ponderhit"] => Ok(UciCommand::PonderHit),
+        ["quit"] => Ok(UciCommand::Quit),
+        [] => Err(String::from("Empty command")),
+        _ => Err(format!("Unknown command: '{}'", line.trim())),
+    }
+}
+
+// "setoption name <id> [value <x>]". Both the name and the value may
+// contain spaces.
+fn parse_setoption(args: &[&str]) -> Result<UciCommand, String> {
+    let ["name", rest @ ..] = args else {
+        return Err(String::from("Expected: setoption name <id> [value <x>]"));
+    };
+    let (name, value) = match rest.iter().position(|&t| t == "value") {
+        Some(i) => (&rest[..i], Some(rest[i + 1..].join(" "))),
+        None => (rest, None),
+    };
+    if name.is_empty() {
+        return Err(String::from("No option name given"));
+    }
+
+    Ok(UciCommand::SetOption {
+        name: name.join(" "),
+        value,
+    })
+}
+
+// "position startpos [moves <move> ...]" or
+// "position fen <fen> [moves <move> ...]".
+fn parse_position(args: &[&str]) -> Result<UciCommand, String> {
+    let end = args
+        .iter()
+        .position(|&t| t == "moves")
+        .unwrap_or(args.len());
+    let fen = match &args[..end] {
+        ["startpos"] => None,
+        ["fen"] => return Err(String::from("No FEN given")),
+        ["fen", fen @ ..] => Some(fen.join(" ")),
+        _ => {
+            return Err(String::from(
+                "Expected: position startpos | fen <fen> [moves ...]",
+            ));
+        }
+    };
+    let moves = args
+        .get(end + 1..)
+        .unwrap_or_default()
+        .iter()
+        .map(|&m| parse_move(m))
+        .collect::<Result<Vec<String>, String>>()?;
+
+    Ok(UciCommand::Position { fen, moves })
+}
+
+fn parse_go(args: &[&str]) -> Result<GoParams, String> {
+    let mut params = GoParams::default();
+    let mut tokens = args.iter().copied().peekable();
+
+    while let Some(token) = tokens.next() {
+        let mut value = || tokens.next().ok_or(format!("No value given for {}", token));
+        match token {
+            "infinite" => params.infinite = true,
+            "ponder" => params.ponder = true,
+            "wtime" => params.wtime = Some(parse_number(token, value()?)?),
+            "btime" => params.btime = Some(parse_number(token, value()?)?),
+            "winc" => params.winc = Some(parse_number(token, value()?)?),
+            "binc" => params.binc = Some(parse_number(token, value()?)?),
+            "movestogo" => params.moves_to_go = Some(parse_number(token, value()?)?),
+            "depth" => params.depth = Some(parse_number(token, value()?)?),
+            "nodes" => params.nodes = Some(parse_number(token, value()?)?),
+            "mate" => params.mate = Some(parse_number(token, value()?)?),
+            "movetime" => params.move_time = Some(parse_number(token, value()?)?),
+            "searchmoves" => {
+                // The moves run up to the next option, if any.
+                while let Some(m) = tokens.next_if(|t| !GO_OPTIONS.contains(t)) {
+                    params.search_moves.push(parse_move(m)?);
+                }
+            }
+            _ => return Err(format!("Unknown go option: '{}'", token)),
+        }
+    }
+
+    Ok(params)
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: '{}'", name, value))
+}
+
+// A move in UCI notation: from and to squares, and a promotion piece.
+fn parse_move(token: &str) -> Result<String, String> {
+    let square =
+        |file: u8, rank: u8| (b'a'..=b'h').contains(&file) && (b'1'..=b'8').contains(&rank);
+    let valid = match token.as_bytes() {
+        [f1, r1, f2, r2] => square(*f1, *r1) && square(*f2, *r2),
+        [f1, r1, f2, r2, p] => square(*f1, *r1) && square(*f2, *r2) && b"qrbn".contains(p),
+        _ => false,
+    };
+
+    if valid {
+        Ok(token.to_string())
+    } else {
+        Err(format!("Invalid move: '{}'", token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GoParams, UciCommand, parse};
+
+    fn strings(moves: &[&str]) -> Vec<String> {
+        moves.iter().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn valid_commands() {
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let cases: Vec<(String, UciCommand)> = vec![
+            ("uci".into(), UciCommand::Uci),
+            ("  isready  ".into(), UciCommand::IsReady),
+            ("ucinewgame".into(), UciCommand::UciNewGame),
+            ("stop".into(), UciCommand::Stop),
+            ("ponderhit".into(), UciCommand::PonderHit),
+            ("quit".into(), UciCommand::Quit),
+            (
+                "setoption name Hash value 64".into(),
+                UciCommand::SetOption {
+                    name: "Hash".into(),
+                    value: Some("64".into()),
+                },
+            ),
+            (
+                "setoption name Clear Hash".into(),
+                UciCommand::SetOption {
+                    name: "Clear Hash".into(),
+                    value: None,
+                },
+            ),
+            (
+                "setoption name EvalFile value nets/my net.bin".into(),
+                UciCommand::SetOption {
+                    name: "EvalFile".into(),
+                    value: Some("nets/my net.bin".into()),
+                },
+            ),
+            (
+                "setoption name EvalFile value".into(),
+                UciCommand::SetOption {
+                    name: "EvalFile".into(),
+                    value: Some("".into()),
+                },
+            ),
+            (
+                "position startpos".into(),
+                UciCommand::Position {
+                    fen: None,
+                    moves: vec![],
+                },
+            ),
+            (
+                "position startpos moves".into(),
+                UciCommand::Position {
+                    fen: None,
+                    moves: vec![],
+                },
+            ),
+            (
+                "position startpos moves e2e4 e7e5 g1f3".into(),
+                UciCommand::Position {
+                    fen: None,
+                    moves: strings(&["e2e4", "e7e5", "g1f3"]),
+                },
+            ),
+            (
+                format!("position fen {kiwipete}"),
+                UciCommand::Position {
+                    fen: Some(kiwipete.into()),
+                    moves: vec![],
+                },
+            ),
+            (
+                format!("position fen {kiwipete} moves e1g1 h3g2 d5d6"),
+                UciCommand::Position {
+                    fen: Some(kiwipete.into()),
+                    moves: strings(&["e1g1", "h3g2", "d5d6"]),
+                },
+            ),
+            (
+                "position fen 8/P7/8/8/8/8/8/k6K w - - moves a7a8q".into(),
+                UciCommand::Position {
+                    fen: Some("8/P7/8/8/8/8/8/k6K w - -".into()),
+                    moves: strings(&["a7a8q"]),
+                },
+            ),
+            ("go".into(), UciCommand::Go(GoParams::default())),
+            (
+                "go infinite".into(),
+                UciCommand::Go(GoParams {
+                    infinite: true,
+                    ..GoParams::default()
+                }),
+            ),
+            (
+                "go ponder wtime 1000 btime -50 winc 10 binc 20 movestogo 30".into(),
+                UciCommand::Go(GoParams {
+                    ponder: true,
+                    wtime: Some(1000),
+                    btime: Some(-50),
+                    winc: Some(10),
+                    binc: Some(20),
+                    moves_to_go: Some(30),
+                    ..GoParams::default()
+                }),
+            ),
+            (
+                "go depth 12".into(),
+                UciCommand::Go(GoParams {
+                    depth: Some(12),
+                    ..GoParams::default()
+                }),
+            ),
+            (
+                "go nodes 100000".into(),
+                UciCommand::Go(GoParams {
+                    nodes: Some(100_000),
+                    ..GoParams::default()
+                }),
+            ),
+            (
+                "go mate 3".into(),
+                UciCommand::Go(GoParams {
+                    mate: Some(3),
+                    ..GoParams::default()
+                }),
+            ),
+            (
+                "go movetime 500".into(),
+                UciCommand::Go(GoParams {
+                    move_time: Some(500),
+                    ..GoParams::default()
+                }),
+            ),
+            (
+                "go searchmoves e2e4 d2d4".into(),
+                UciCommand::Go(GoParams {
+                    search_moves: strings(&["e2e4", "d2d4"]),
+                    ..GoParams::default()
+                }),
+            ),
+            (
+                "go searchmoves e2e4 d2d4 depth 5 infinite".into(),
+                UciCommand::Go(GoParams {
+                    search_moves: strings(&["e2e4", "d2d4"]),
+                    depth: Some(5),
+                    infinite: true,
+                    ..GoParams::default()
+                }),
+            ),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(parse(&line), Ok(expected), "{line}");
+        }
+    }
+
+    #[test]
+    fn invalid_commands() {
+        let cases = [
+            "",
+            "   ",
+            "foo",
+            "uci now",
+            "isready please",
+            "setoption",
+            "setoption Hash value 64",
+            "setoption name",
+            "setoption name value 64",
+            "position",
+            "position moves e2e4",
+            "position startpos e2e4",
+            "position fen",
+            "position fen moves e2e4",
+            "position startpos moves e2e9",
+            "position startpos moves e2e4 e7e8k",
+            "position startpos moves e2",
+            "go depth",
+            "go depth deep",
+            "go depth 1000",
+            "go nodes -1",
+            "go winc -10",
+            "go movetime",
+            "go searchmoves e2e4 x",
+            "go fast",
+        ];
+
+        for line in cases {
+            assert!(parse(line).is_err(), "{line}");
+        }
+    }
+}
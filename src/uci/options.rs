@@ -0,0 +1,243 @@
+use crate::search::transposition::{DEFAULT_HASH_MB, MAX_HASH_MB};
+use crate::search::{DEFAULT_MULTI_PV, DEFAULT_THREADS, MAX_MULTI_PV, MAX_THREADS};
+use std::fmt;
+
+// The options the engine offers to the GUI. Each one is described once
+// here: its name, its type and its default value. The GUI learns about them
+// from the "option" lines sent in reply to "uci", and "setoption" values are
+// checked against them before the engine uses them.
+
+pub enum OptionType {
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    Check {
+        default: bool,
+    },
+    Combo {
+        default: &'static str,
+        vars: &'static [&'static str],
+    },
+    String {
+        default: &'static str,
+    },
+    Button,
+}
+
+pub struct UciOption {
+    pub name: &'static str,
+    pub kind: OptionType,
+}
+
+// A value for an option, checked against its type.
+#[derive(Debug, PartialEq)]
+pub enum OptionValue {
+    Spin(i64),
+    Check(bool),
+    Combo(&'static str),
+    String(String),
+    Button,
+}
+
+// The instruction sets the NNUE evaluation can be limited to.
+const SIMD_VARS: [&str; 5] = ["Auto", "AVX-512", "AVX2", "SSE4.1", "Scalar"];
+
+pub const OPTIONS: [UciOption; 13] = [
+    UciOption {
+        name: "Hash",
+        kind: OptionType::Spin {
+            default: DEFAULT_HASH_MB as i64,
+            min: 1,
+            max: MAX_HASH_MB as i64,
+        },
+    },
+    UciOption {
+        name: "Clear Hash",
+        kind: OptionType::Button,
+    },
+    UciOption {
+        name: "Threads",
+        kind: OptionType::Spin {
+            default: DEFAULT_THREADS as i64,
+            min: 1,
+            max: MAX_THREADS as i64,
+        },
+    },
+    UciOption {
+        name: "MultiPV",
+        kind: OptionType::Spin {
+            default: DEFAULT_MULTI_PV as i64,
+            min: 1,
+            max: MAX_MULTI_PV as i64,
+        },
+    },
+    UciOption {
+        name: "Ponder",
+        kind: OptionType::Check { default: false },
+    },
+    UciOption {
+        name: "EvalFile",
+        kind: OptionType::String { default: "" },
+    },
+    UciOption {
+        name: "UseNNUE",
+        kind: OptionType::Check { default: false },
+    },
+    UciOption {
+        name: "SIMD",
+        kind: OptionType::Combo {
+            default: "Auto",
+            vars: &SIMD_VARS,
+        },
+    },
+    // The selective search techniques.
+    UciOption {
+        name: "LateMoveReductions",
+        kind: OptionType::Check { default: true },
+    },
+    UciOption {
+        name: "ReverseFutilityPruning",
+        kind: OptionType::Check { default: true },
+    },
+    UciOption {
+        name: "FutilityPruning",
+        kind: OptionType::Check { default: true },
+    },
+    UciOption {
+        name: "LateMovePruning",
+        kind: OptionType::Check { default: true },
+    },
+    UciOption {
+        name: "CheckExtensions",
+        kind: OptionType::Check { default: true },
+    },
+];
+
+// Option names are not case sensitive.
+pub fn find(name: &str) -> Option<&'static UciOption> {
+    OPTIONS.iter().find(|o| o.name.eq_ignore_ascii_case(name))
+}
+
+// Check the name and value given with "setoption".
+pub fn parse_setoption(
+    name: &str,
+    value: Option<&str>,
+) -> Result<(&'static UciOption, OptionValue), String> {
+    let option = find(name).ok_or_else(|| format!("Unknown option: '{}'", name))?;
+    let value = option.parse(value)?;
+    Ok((option, value))
+}
+
+impl UciOption {
+    // Check a value given with "setoption".
+    pub fn parse(&self, value: Option<&str>) -> Result<OptionValue, String> {
+        let name = self.name;
+        if let OptionType::Button = self.kind {
+            return Ok(OptionValue::Button);
+        }
+        let Some(value) = value else {
+            return Err(format!("No value given for {}", name));
+        };
+
+        match self.kind {
+            OptionType::Spin { min, max, .. } => match value.parse::<i64>() {
+                Ok(v) if (min..=max).contains(&v) => Ok(OptionValue::Spin(v)),
+                Ok(_) => Err(format!(
+                    "Value for {} out of range [{}, {}]: '{}'",
+                    name, min, max, value
+                )),
+                Err(_) => Err(format!("Invalid value for {}: '{}'", name, value)),
+            },
+            OptionType::Check { .. } => match value {
+                "true" => Ok(OptionValue::Check(true)),
+                "false" => Ok(OptionValue::Check(false)),
+                _ => Err(format!(
+                    "Invalid value for {}: '{}' (expected true or false)",
+                    name, value
+                )),
+            },
+            OptionType::Combo { vars, .. } => vars
+                .iter()
+                .find(|v| v.eq_ignore_ascii_case(value))
+                .map(|v| OptionValue::Combo(v))
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid value for {}: '{}' (expected one of {})",
+                        name,
+                        value,
+                        vars.join(", ")
+                    )
+                }),
+            OptionType::String { .. } => Ok(OptionValue::String(value.to_string())),
+            OptionType::Button => unreachable!(),
+        }
+    }
+}
+
+// The "option" line that advertises the option to the GUI. An empty
+// string is sent as "<empty>".
+impl fmt::Display for UciOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+        match self.kind {
+            OptionType::Spin { default, min, max } => {
+                write!(f, "spin default {} min {} max {}", default, min, max)
+            }
+            OptionType::Check { default } => write!(f, "check default {}", default),
+            OptionType::Combo { default, vars } => {
+                write!(f, "combo default {}", default)?;
+                vars.iter().try_for_each(|v| write!(f, " var {}", v))
+            }
+            OptionType::String { default: "" } => write!(f, "string default <empty>"),
+            OptionType::String { default } => write!(f, "string default {}", default),
+            OptionType::Button => write!(f, "button"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OPTIONS, OptionValue, find, parse_setoption};
+
+    #[test]
+    fn defaults_are_valid() {
+        for option in &OPTIONS {
+            let line = option.to_string();
+            assert!(line.starts_with(&format!("option name {} type ", option.name)));
+            if let Some((_, default)) = line.split_once(" default ") {
+                let default = default.split(" min ").next().unwrap();
+                let default = default.split(" var ").next().unwrap();
+                let default = if default == "<empty>" { "" } else { default };
+                assert!(option.parse(Some(default)).is_ok(), "{line}");
+            }
+        }
+    }
+
+    #[test]
+    fn setoption() {
+        let (option, value) = parse_setoption("Hash", Some("64")).unwrap();
+        assert_eq!((option.name, value), ("Hash", OptionValue::Spin(64)));
+        let (option, value) = parse_setoption("clear hash", None).unwrap();
+        assert_eq!((option.name, value), ("Clear Hash", OptionValue::Button));
+        let (_, value) = parse_setoption("EvalFile", Some("my nets/a.bin")).unwrap();
+        assert_eq!(value, OptionValue::String(String::from("my nets/a.bin")));
+        let (_, value) = parse_setoption("SIMD", Some("avx2")).unwrap();
+        assert_eq!(value, OptionValue::Combo("AVX2"));
+        let (_, value) = parse_setoption("UseNNUE", Some("true")).unwrap();
+        assert_eq!(value, OptionValue::Check(true));
+
+        for (name, value) in [
+            ("Hash", None),
+            ("Hash", Some("0")),
+            ("Hash", Some("lots")),
+            ("UseNNUE", Some("yes")),
+            ("SIMD", Some("MMX")),
+            ("NoSuchOption", Some("1")),
+        ] {
+            assert!(parse_setoption(name, value).is_err(), "{name} {value:?}");
+        }
+        assert!(find("multipv").is_some());
+    }
+}